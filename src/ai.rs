@@ -0,0 +1,406 @@
+use std::fmt;
+
+use bevy::prelude::*;
+use crate::components::*;
+use crate::constants::*;
+use crate::power::PowerUp;
+use crate::resources::*;
+use crate::targeting::{candidates, swap_indexes, Target, TargetContext};
+use crate::turn_setup::{apply_move, generate_moves, MarbleState};
+
+/// Why a move was suggested.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveReason {
+    Captures(Player),
+    EscapesThreat(Player),
+    ReachesHome,
+    LeavesBase,
+    TakesShortcut,
+    Advances,
+}
+
+impl fmt::Display for MoveReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveReason::Captures(player) => write!(f, "captures {:?}", player),
+            MoveReason::EscapesThreat(player) => write!(f, "escapes threat from {:?}", player),
+            MoveReason::ReachesHome => write!(f, "gets a marble home"),
+            MoveReason::LeavesBase => write!(f, "gets a marble out of the base"),
+            MoveReason::TakesShortcut => write!(f, "takes the center shortcut"),
+            MoveReason::Advances => write!(f, "moves the furthest"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Suggestion {
+    pub entity: Entity,
+    pub marble_move: MarbleMove,
+    pub reason: MoveReason,
+    pub score: f32,
+}
+
+/// Every distinct roll of two dice along with its chance of being rolled.
+pub fn all_rolls() -> impl Iterator<Item = (Dice, f32)> {
+    (1..=6u8).flat_map(|one| (one..=6).map(move |two| {
+        let outcomes = if one == two { 1.0 } else { 2.0 };
+        (Dice::new(one, two), outcomes / 36.0)
+    }))
+}
+
+/// The chance each opponent has of landing on `target` with their next roll.
+/// Opponents that can't reach it are left out.
+pub fn capture_threats(target: &MarbleState, board: &[MarbleState], settings: &GameSettings) -> Vec<(Player, f32)> {
+    // only marbles on the track or in the center can be captured
+    if !(target.index < FIRST_HOME_INDEX || target.index == CENTER_INDEX) {
+        return Vec::new();
+    }
+    let status = PowerUpStatus::default();
+    [Player::Red, Player::Green, Player::Blue, Player::Yellow].into_iter()
+        .filter(|p| *p != target.player && !target.player.is_teammate(*p, settings.team_play))
+        .filter_map(|opponent| {
+            let chance: f32 = all_rolls()
+                .filter(|(dice, _)| generate_moves(opponent, board, dice, &status, settings).iter()
+                    .any(|(_, m)| {
+                        (m.destination < FIRST_HOME_INDEX || m.destination == CENTER_INDEX) &&
+                        Player::is_same_index(opponent, m.destination, target.player, target.index)
+                    })
+                )
+                .map(|(_, chance)| chance)
+                .sum();
+            if chance > 0.0 { Some((opponent, chance)) } else { None }
+        })
+        .collect()
+}
+
+/// The opponent most likely to capture `target` along with the chance they
+/// have of doing it.
+fn biggest_threat(target: &MarbleState, board: &[MarbleState], settings: &GameSettings) -> Option<(Player, f32)> {
+    capture_threats(target, board, settings).into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+/// Scores a single move for the `owner` - the higher the better.
+fn score_move(
+    owner: Player,
+    board: &[MarbleState],
+    entity: Entity,
+    marble_move: MarbleMove,
+    settings: &GameSettings,
+) -> (f32, MoveReason) {
+    let Some(before) = board.iter().find(|m| m.entity == entity) else {
+        return (f32::MIN, MoveReason::Advances);
+    };
+    let next_board = apply_move(owner, board, entity, marble_move.destination, settings);
+    let after = next_board.iter().find(|m| m.entity == entity).unwrap();
+
+    let captured = board.iter().zip(next_board.iter())
+        .find(|(old, new)| old.entity != entity && old.index != new.index)
+        .map(|(old, _)| old.player);
+    let threat_before = biggest_threat(before, board, settings);
+    let threat_after = biggest_threat(after, &next_board, settings);
+    let home = (FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&after.index) &&
+               !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&before.index);
+    let leaves_base = before.index == BOARD.len();
+    let shortcut = after.index == CENTER_INDEX;
+
+    let mut score = marble_move.distance as f32;
+    if captured.is_some() { score += 40.0; }
+    if home { score += 25.0; }
+    if leaves_base { score += 15.0; }
+    if shortcut { score += 10.0; }
+    score += 30.0 * threat_before.map_or(0.0, |(_, chance)| chance);
+    score -= 30.0 * threat_after.map_or(0.0, |(_, chance)| chance);
+
+    let reason = match (captured, threat_before, threat_after) {
+        (Some(player), _, _) => MoveReason::Captures(player),
+        (_, Some((player, _)), None) => MoveReason::EscapesThreat(player),
+        _ if home => MoveReason::ReachesHome,
+        _ if leaves_base => MoveReason::LeavesBase,
+        _ if shortcut => MoveReason::TakesShortcut,
+        _ => MoveReason::Advances,
+    };
+    (score, reason)
+}
+
+/// Picks the best of the `possible_moves` for the `owner`.
+pub fn suggest_move(
+    owner: Player,
+    board: &[MarbleState],
+    possible_moves: &[(Entity, MarbleMove)],
+    settings: &GameSettings,
+) -> Option<Suggestion> {
+    possible_moves.iter()
+        .map(|(entity, marble_move)| {
+            let (score, reason) = score_move(owner, board, *entity, *marble_move, settings);
+            Suggestion{ entity: *entity, marble_move: *marble_move, reason, score }
+        })
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+}
+
+/// Finds a power-up the player is holding that would open up a better move
+/// than `best` (or any move at all if there is none).
+pub fn helpful_power_up(
+    owner: Player,
+    board: &[MarbleState],
+    dice: &Dice,
+    player_data: &PlayerData,
+    best: Option<&Suggestion>,
+    settings: &GameSettings,
+) -> Option<PowerUp> {
+    let best_score = best.map_or(f32::MIN, |s| s.score);
+    player_data.power_ups.iter().flatten()
+        .map(|(power_up, _)| *power_up)
+        .find(|power_up| {
+            let mut status = player_data.power_up_status.clone();
+            let mut dice = dice.clone();
+            match power_up {
+                // a new roll is only worth it when there's nothing to do with this one
+                PowerUp::RollAgain => return best.is_none(),
+                PowerUp::EvadeCapture => return false,
+                PowerUp::DoubleDice if !dice.is_empty() => dice.multiplier = 2,
+                PowerUp::DoubleDice => return false,
+                PowerUp::SelfJump => status.jump_self_turns = 1,
+                PowerUp::CaptureNearest => status.capture_nearest = true,
+                PowerUp::HomeRun => status.home_run = true,
+                // worth it when it gains at least a quarter of a lap on someone
+                PowerUp::Swap => {
+                    let ctx = TargetContext{ owner, board, frozen: Vec::new(), settings };
+                    return best_swap(&ctx, None).is_some_and(|(_, _, gain)| gain >= 12);
+                }
+                // worth it when an opponent is likely to capture one of your marbles
+                PowerUp::Freeze | PowerUp::Shield => return board.iter()
+                    .filter(|m| m.player == owner)
+                    .any(|m| biggest_threat(m, board, settings).is_some_and(|(_, chance)| chance > 0.3)),
+            }
+            let moves = generate_moves(owner, board, &dice, &status, settings);
+            // only count it as helping if it's clearly better
+            suggest_move(owner, board, &moves, settings).is_some_and(|s| s.score > best_score + 10.0)
+        })
+}
+
+/// What a power-up is worth to the `owner` right now. Targeted power-ups are
+/// worth more when there's a good target for them, the rest are ranked by how
+/// much they usually help.
+fn power_up_values(owner: Player, board: &[MarbleState], settings: &GameSettings) -> impl Fn(&PowerUp) -> f32 {
+    let ctx = TargetContext{ owner, board, frozen: Vec::new(), settings };
+    let swap_gain = best_swap(&ctx, None).map_or(0.0, |(_, _, gain)| gain as f32);
+    // the chance of the player's most threatened marble being captured
+    let threat = board.iter()
+        .filter(|m| m.player == owner)
+        .filter_map(|m| biggest_threat(m, board, settings))
+        .map(|(_, chance)| chance)
+        .fold(0.0, f32::max);
+    move |power_up: &PowerUp| match power_up {
+        PowerUp::RollAgain => 3.0,
+        PowerUp::DoubleDice => 5.0,
+        PowerUp::EvadeCapture => 4.0 + 15.0 * threat,
+        PowerUp::SelfJump => 4.0,
+        PowerUp::CaptureNearest => 8.0,
+        PowerUp::HomeRun => 12.0,
+        PowerUp::Swap => swap_gain / 3.0,
+        PowerUp::Freeze => 2.0 + 25.0 * threat,
+        PowerUp::Shield => 3.0 + 30.0 * threat,
+    }
+}
+
+/// How the computer drafts a power-up: the one of the `options` worth the
+/// most to the `owner` right now.
+pub fn draft_power_up(owner: Player, board: &[MarbleState], options: &[PowerUp], settings: &GameSettings) -> PowerUp {
+    let value = power_up_values(owner, board, settings);
+    *options.iter()
+        .max_by(|a, b| value(a).total_cmp(&value(b)))
+        .expect("drafts always offer power-ups")
+}
+
+/// How the computer makes room when its power bar is full: the slot holding
+/// the power-up worth the least, if it's worth less than a new power-up
+/// usually is. `None` keeps them all.
+pub fn discard_power_up(owner: Player, board: &[MarbleState], held: &[Option<PowerUp>], settings: &GameSettings) -> Option<usize> {
+    const AVERAGE_VALUE: f32 = 5.0;
+    let value = power_up_values(owner, board, settings);
+    held.iter().enumerate()
+        .filter_map(|(i, p)| p.map(|p| (i, value(&p))))
+        .filter(|(_, v)| *v < AVERAGE_VALUE)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| i)
+}
+
+/// Picks the swap that gains the most ground for the player: how far their
+/// marble jumps ahead plus how far the opponent's is set back. It can be
+/// limited to swaps with a marble that's already been picked.
+pub fn best_swap(ctx: &TargetContext, own: Option<Entity>) -> Option<(Entity, Entity, i32)> {
+    candidates(PowerUp::Swap, &[], ctx).into_iter()
+        .filter(|t| own.is_none_or(|own| *t == Target::Marble(own)))
+        .flat_map(|mine| candidates(PowerUp::Swap, &[mine], ctx).into_iter().map(move |theirs| (mine, theirs)))
+        .filter_map(|targets| match targets {
+            (Target::Marble(mine), Target::Marble(theirs)) => Some((ctx.marble(mine)?, ctx.marble(theirs)?)),
+            _ => None,
+        })
+        .map(|(a, b)| {
+            let (new_a, new_b) = swap_indexes(a, b);
+            (a.entity, b.entity, (new_a as i32 - a.index as i32) + (b.index as i32 - new_b as i32))
+        })
+        .max_by_key(|(_, _, gain)| *gain)
+}
+
+/// How much the player would like to freeze `opponent`: mostly how likely
+/// they are to capture one of the player's marbles, then how far along they
+/// are.
+fn freeze_score(opponent: Player, ctx: &TargetContext) -> f32 {
+    let threat: f32 = ctx.board.iter()
+        .filter(|m| m.player == ctx.owner)
+        .flat_map(|m| capture_threats(m, ctx.board, ctx.settings))
+        .filter(|(p, _)| *p == opponent)
+        .map(|(_, chance)| chance)
+        .sum();
+    let progress: usize = ctx.board.iter()
+        .filter(|m| m.player == opponent && m.index != BOARD.len())
+        .map(|m| m.index)
+        .sum();
+    threat + progress as f32 / 1000.0
+}
+
+/// How the computer aims a power-up: the next target to pick after the ones
+/// already `picked`, if there's anything to pick.
+pub fn choose_target(power_up: PowerUp, picked: &[Target], ctx: &TargetContext) -> Option<Target> {
+    match (power_up, picked) {
+        (PowerUp::Swap, []) => best_swap(ctx, None).map(|(mine, _, _)| Target::Marble(mine)),
+        (PowerUp::Swap, [Target::Marble(mine)]) => best_swap(ctx, Some(*mine)).map(|(_, theirs, _)| Target::Marble(theirs)),
+        (PowerUp::Freeze, []) => candidates(power_up, picked, ctx).into_iter()
+            .filter_map(|t| match t {
+                Target::Player(p) => Some((t, freeze_score(p, ctx))),
+                _ => None,
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(t, _)| t),
+        // shield the marble most likely to be captured
+        (PowerUp::Shield, []) => candidates(power_up, picked, ctx).into_iter()
+            .filter_map(|t| match t {
+                Target::Marble(e) => {
+                    let marble = ctx.marble(e)?;
+                    let threat = biggest_threat(marble, ctx.board, ctx.settings).map_or(0.0, |(_, chance)| chance);
+                    Some((t, threat + marble.index as f32 / 1000.0))
+                }
+                _ => None,
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(t, _)| t),
+        _ => candidates(power_up, picked, ctx).first().copied(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn marble(id: u32, player: Player, index: usize) -> MarbleState {
+        MarbleState{ entity: Entity::from_raw(id), player, index, evading: false }
+    }
+
+    fn mv(destination: usize, distance: usize, which: WhichDie) -> MarbleMove {
+        (destination, distance, which).into()
+    }
+
+    #[test]
+    fn test_prefers_capture() {
+        // red marble 1 can capture the green marble 3 tiles ahead, marble 2 can just move further
+        let board = [
+            marble(1, Player::Red, 10),
+            marble(2, Player::Red, 20),
+            marble(3, Player::Green, Player::shift_index(13, Player::Red, Player::Green)),
+        ];
+        let moves = [
+            (Entity::from_raw(1), mv(13, 3, WhichDie::One)),
+            (Entity::from_raw(2), mv(24, 4, WhichDie::Two)),
+        ];
+        let settings = GameSettings::default();
+        let suggestion = suggest_move(Player::Red, &board, &moves, &settings).unwrap();
+        assert_eq!(Entity::from_raw(1), suggestion.entity);
+        assert_eq!(MoveReason::Captures(Player::Green), suggestion.reason);
+    }
+
+    #[test]
+    fn test_capture_threats() {
+        // green marble sits 4 tiles behind the red marble
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, Player::shift_index(26, Player::Red, Player::Green)),
+        ];
+        let settings = GameSettings::default();
+        let threats = capture_threats(&board[0], &board, &settings);
+        assert_eq!(1, threats.len());
+        assert_eq!(Player::Green, threats[0].0);
+        // a 4 on either die (11 rolls) or a sum of 4 without a 4 (1+3, 3+1, 2+2)
+        assert!((threats[0].1 - 14.0 / 36.0).abs() < 0.001);
+
+        // evading marbles can't be captured
+        let mut evading = board;
+        evading[0].evading = true;
+        assert!(capture_threats(&evading[0], &evading, &settings).is_empty());
+    }
+
+    #[test]
+    fn test_best_swap() {
+        // red jumps from 5 to 40 while green goes from its 28 to its 41
+        let board = [
+            marble(1, Player::Red, 5),
+            marble(2, Player::Red, 45),
+            marble(3, Player::Green, Player::shift_index(40, Player::Red, Player::Green)),
+        ];
+        let settings = GameSettings::default();
+        let ctx = TargetContext{ owner: Player::Red, board: &board, frozen: Vec::new(), settings: &settings };
+        assert_eq!(Some((Entity::from_raw(1), Entity::from_raw(3), 35 - 13)), best_swap(&ctx, None));
+        // the computer picks the same two marbles in turn
+        let mine = Target::Marble(Entity::from_raw(1));
+        assert_eq!(Some(mine), choose_target(PowerUp::Swap, &[], &ctx));
+        assert_eq!(Some(Target::Marble(Entity::from_raw(3))), choose_target(PowerUp::Swap, &[mine], &ctx));
+    }
+
+    #[test]
+    fn test_draft_power_up() {
+        let options = [PowerUp::DoubleDice, PowerUp::HomeRun, PowerUp::Shield];
+        let settings = GameSettings::default();
+        // nothing is threatening red, so the home run is worth the most
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, Player::shift_index(10, Player::Red, Player::Green)),
+        ];
+        assert_eq!(PowerUp::HomeRun, draft_power_up(Player::Red, &board, &options, &settings));
+
+        // green sitting 4 tiles behind makes the shield worth more
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, Player::shift_index(26, Player::Red, Player::Green)),
+        ];
+        assert_eq!(PowerUp::Shield, draft_power_up(Player::Red, &board, &options, &settings));
+    }
+
+    #[test]
+    fn test_discard_power_up() {
+        let settings = GameSettings::default();
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, Player::shift_index(10, Player::Red, Player::Green)),
+        ];
+        // the roll again is worth the least
+        let held = [Some(PowerUp::HomeRun), Some(PowerUp::RollAgain), Some(PowerUp::DoubleDice)];
+        assert_eq!(Some(1), discard_power_up(Player::Red, &board, &held, &settings));
+        // all of these are worth keeping
+        let held = [Some(PowerUp::HomeRun), Some(PowerUp::CaptureNearest), None];
+        assert_eq!(None, discard_power_up(Player::Red, &board, &held, &settings));
+    }
+
+    #[test]
+    fn test_freezes_biggest_threat() {
+        // green sits 4 tiles behind red's marble while yellow is further along but can't reach it
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, Player::shift_index(26, Player::Red, Player::Green)),
+            marble(3, Player::Yellow, FIRST_HOME_INDEX + 1),
+        ];
+        let settings = GameSettings::default();
+        let ctx = TargetContext{ owner: Player::Red, board: &board, frozen: Vec::new(), settings: &settings };
+        assert_eq!(Some(Target::Player(Player::Green)), choose_target(PowerUp::Freeze, &[], &ctx));
+    }
+}
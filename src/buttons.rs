@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 use crate::constants::*;
+use crate::input::{Bindings, InputAction};
 
 /// An `ActionEvent` that is sent when a button is clicked. The type `T` defines
 /// what those actions really are.
@@ -23,12 +24,21 @@ pub struct ButtonSize(pub Vec2);
 #[derive(Component)]
 pub struct Hidable;
 
+/// Marks a button that can't be pressed right now.
+#[derive(Component)]
+pub struct Disabled;
+
+/// Marks a button that is drawn with text instead of a sprite sheet.
+#[derive(Component)]
+pub struct TextButton;
+
 /// This system is responsible for changing button states based on the mouse location and its
 /// button status.
 pub fn mouse_watcher<T: Copy + Send + Sync + 'static>(
     mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
     mut cursor_moved_events: EventReader<CursorMoved>,
-    mut button_query: Query<(&mut ButtonState, &ButtonAction<T>, &Transform, &ButtonSize)>,
+    mut button_query: Query<(&mut ButtonState, &ButtonAction<T>, &Transform, &ButtonSize), Without<Disabled>>,
     mut action_events: EventWriter<ActionEvent<T>>,
 ) {
     let cursor_move_event = cursor_moved_events.iter().last();
@@ -41,7 +51,7 @@ pub fn mouse_watcher<T: Copy + Send + Sync + 'static>(
                 }
             }
             (ButtonState::Hovered, moved) => {
-                if mouse_button_inputs.just_pressed(MouseButton::Left) {
+                if mouse_button_inputs.just_pressed(bindings.click) {
                     *button_state = ButtonState::Pressed;
                 } else if let Some(move_event) = moved {
                     if !is_in_bounds(move_event.position, transform.translation, button_size.0) {
@@ -50,7 +60,7 @@ pub fn mouse_watcher<T: Copy + Send + Sync + 'static>(
                 }
             }
             (ButtonState::Pressed, moved) => {
-                if mouse_button_inputs.just_released(MouseButton::Left) {
+                if mouse_button_inputs.just_released(bindings.click) {
                     *button_state = ButtonState::Hovered;
                     action_events.send(action.0)
                 } else if let Some(move_event) = moved {
@@ -60,7 +70,7 @@ pub fn mouse_watcher<T: Copy + Send + Sync + 'static>(
                 }
             }
             (ButtonState::PressedNotHovered, moved) => {
-                if mouse_button_inputs.just_released(MouseButton::Left) {
+                if mouse_button_inputs.just_released(bindings.click) {
                     *button_state = ButtonState::NotHovered;
                 } else if let Some(move_event) = moved {
                     if is_in_bounds(move_event.position, transform.translation, button_size.0) {
@@ -73,8 +83,72 @@ pub fn mouse_watcher<T: Copy + Send + Sync + 'static>(
     }
 }
 
-/// This is a helper function used specifically in this file.
-fn is_in_bounds(cursor_pos: Vec2, button_pos: Vec3, button_size: Vec2) -> bool {
+/// Marks the button that has keyboard (or gamepad) focus.
+#[derive(Component)]
+pub struct Focused;
+
+/// This system is responsible for moving focus between buttons (top to bottom,
+/// left to right) and pressing the focused button from the keyboard or a
+/// gamepad. A focused button looks just like a button the mouse is hovering
+/// over, and flashes its pressed look when it's pressed.
+pub fn focus_watcher<T: Copy + Send + Sync + 'static>(
+    mut commands: Commands,
+    mut input_actions: EventReader<InputAction>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    mut button_query: Query<(Entity, &mut ButtonState, &ButtonAction<T>, &Transform, &Visibility, Option<&Focused>), Without<Disabled>>,
+    mut action_events: EventWriter<ActionEvent<T>>,
+) {
+    // let go of the button pressed last frame (unless the mouse is the one pressing it)
+    if !mouse_button_inputs.pressed(bindings.click) {
+        for (_, mut state, .., focused) in &mut button_query {
+            if focused.is_some() && matches!(*state, ButtonState::Pressed) {
+                *state = ButtonState::Hovered;
+            }
+        }
+    }
+
+    for input_action in input_actions.iter() {
+        let step: isize = match input_action {
+            InputAction::Next | InputAction::NextTarget => 1,
+            InputAction::Previous | InputAction::PreviousTarget => -1,
+            InputAction::Confirm => {
+                if let Some((_, mut state, action, ..)) = button_query.iter_mut().find(|(.., focused)| focused.is_some()) {
+                    *state = ButtonState::Pressed;
+                    action_events.send(action.0);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        let mut buttons: Vec<(Entity, Vec3, bool)> = button_query.iter()
+            .filter(|(_, _, _, _, visibility, _)| **visibility != Visibility::Hidden)
+            .map(|(e, _, _, t, _, focused)| (e, t.translation, focused.is_some()))
+            .collect();
+        if buttons.is_empty() {
+            continue;
+        }
+        buttons.sort_by(|a, b| b.1.y.total_cmp(&a.1.y).then(a.1.x.total_cmp(&b.1.x)));
+        let next = match buttons.iter().position(|(_, _, focused)| *focused) {
+            Some(i) => (i as isize + step).rem_euclid(buttons.len() as isize) as usize,
+            None => 0,
+        };
+        for (i, (entity, _, focused)) in buttons.iter().enumerate() {
+            let (_, mut state, ..) = button_query.get_mut(*entity).unwrap();
+            if i == next {
+                commands.entity(*entity).insert(Focused);
+                *state = ButtonState::Hovered;
+            } else if *focused {
+                commands.entity(*entity).remove::<Focused>();
+                *state = ButtonState::NotHovered;
+            }
+        }
+    }
+}
+
+/// This is a helper function used to check if the cursor is over a button.
+pub fn is_in_bounds(cursor_pos: Vec2, button_pos: Vec3, button_size: Vec2) -> bool {
     let (x, y) = (cursor_pos.x - WINDOW_SIZE / 2.0, -(cursor_pos.y - WINDOW_SIZE / 2.0));
     x > button_pos.x - button_size.x / 2.0 &&
     x < button_pos.x + button_size.x / 2.0 &&
@@ -118,6 +192,30 @@ pub fn watch_button_state_changes(
     }
 }
 
+/// This system is responsible for reacting to text button state changes.
+pub fn watch_text_button_state_changes(
+    mut button_query: Query<(&mut Text, &ButtonState, Option<&Disabled>), (Changed<ButtonState>, With<TextButton>)>
+) {
+    for (mut text, state, disabled) in &mut button_query {
+        let color = if disabled.is_some() {
+            Color::rgba(1.0, 1.0, 1.0, 0.4)
+        } else {
+            text_button_color(*state)
+        };
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
+
+fn text_button_color(state: ButtonState) -> Color {
+    match state {
+        ButtonState::NotHovered | ButtonState::PressedNotHovered => Color::WHITE,
+        ButtonState::Hovered => Color::rgb(1.0, 0.85, 0.3),
+        ButtonState::Pressed => Color::rgb(0.8, 0.6, 0.1),
+    }
+}
+
 pub fn spawn_sprite_sheet_button<T: Send + Sync + 'static>(
     parent: &mut ChildBuilder,
     texture_atlas: Handle<TextureAtlas>,
@@ -189,3 +287,31 @@ pub fn load_sprite_sheet(
         asset_server.load(name), size, cols, rows, None, None
     ))
 }
+
+pub fn text_button_bundle<T: Send + Sync + 'static>(
+    text: impl Into<String>,
+    font: Handle<Font>,
+    transform: Transform,
+    action: ButtonAction<T>,
+    button_state: ButtonState,
+    button_size: ButtonSize,
+) -> impl Bundle {
+    (
+        Text2dBundle{
+            text: Text::from_section(
+                text,
+                TextStyle{
+                    font,
+                    font_size: 24.0,
+                    color: text_button_color(button_state),
+                },
+            ),
+            transform,
+            ..default()
+        },
+        button_state,
+        button_size,
+        action,
+        TextButton,
+    )
+}
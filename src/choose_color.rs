@@ -5,6 +5,7 @@ use bevy::window::CursorMoved;
 use bevy::window::PrimaryWindow;
 use crate::components::*;
 use crate::constants::*;
+use crate::input::{Bindings, InputAction};
 use crate::resources::*;
 
 pub struct ChooseColorPlugin;
@@ -14,7 +15,7 @@ impl Plugin for ChooseColorPlugin {
         app
             .add_systems(OnEnter(GameState::ChooseColor), choose_color_setup)
             .add_systems(Update,
-                (mouse_hover_handler, mouse_click_handler)
+                (mouse_hover_handler, mouse_click_handler, keyboard_handler)
                 .run_if(in_state(GameState::ChooseColor))
             )
             .add_systems(OnExit(GameState::ChooseColor), choose_color_cleanup)
@@ -74,34 +75,80 @@ fn position_to_color(pos: Vec2) -> Option<Player> {
 }
 
 fn mouse_click_handler(
-    mut commands: Commands,
-    mut next_state: ResMut<NextState<GameState>>,
+    commands: Commands,
+    next_state: ResMut<NextState<GameState>>,
     windows: Query<&Window, With<PrimaryWindow>>,
     mouse_buttons: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
     asset_server: Res<AssetServer>,
 ) {
-    if mouse_buttons.just_pressed(MouseButton::Left) {
+    if mouse_buttons.just_pressed(bindings.click) {
         let Some(cpos) = windows.get_single().map_or(None, |w| w.cursor_position()) else {
             return;
         };
         if let Some(color) = position_to_color(cpos) {
-            let human_indicator = commands.spawn(SpriteBundle{
-                texture: asset_server.load("human-indicator.png"), // TODO: change indicator for power ups
-                transform: {
-                    let (x, y) = match color {
-                        Player::Red => (-4.0, 4.0),
-                        Player::Green => (4.0, 4.0),
-                        Player::Blue => (4.0, -4.0),
-                        Player::Yellow => (-4.0, -4.0),
-                    };
-                    Transform::from_xyz(x * TILE_SIZE, y * TILE_SIZE, Z_HUMAN_INDICATOR)
-                },
-                ..default()
-            }).id();
-            commands.insert_resource(HumanPlayer{ color, human_indicator });
-            next_state.set(GameState::NextPlayer);
+            choose_color(commands, next_state, asset_server, color);
+        }
+    }
+}
+
+/// The arrow keys (or tab) move the mask around the board clockwise or
+/// counter-clockwise and enter picks the color under it.
+fn keyboard_handler(
+    commands: Commands,
+    mut input_actions: EventReader<InputAction>,
+    mut choose_color_data: ResMut<ChooseColorData>,
+    mask: Query<&mut Transform, With<Mask>>,
+    next_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+) {
+    const ORDER: [Player; 4] = [Player::Red, Player::Green, Player::Blue, Player::Yellow];
+    let mut step = 0;
+    for input_action in input_actions.iter() {
+        match input_action {
+            InputAction::Next | InputAction::NextTarget => step += 1,
+            InputAction::Previous | InputAction::PreviousTarget => step += 3,
+            InputAction::Confirm => {
+                if let Some(color) = choose_color_data.current_player {
+                    choose_color(commands, next_state, asset_server, color);
+                }
+                return;
+            }
+            _ => {}
         }
     }
+    if step == 0 {
+        return;
+    }
+    let next = match choose_color_data.current_player {
+        Some(color) => ORDER[(ORDER.iter().position(|p| *p == color).unwrap() + step) % ORDER.len()],
+        None => ORDER[0],
+    };
+    choose_color_data.current_player = Some(next);
+    show_mask(commands, choose_color_data, mask);
+}
+
+fn choose_color(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    color: Player,
+) {
+    let human_indicator = commands.spawn(SpriteBundle{
+        texture: asset_server.load("human-indicator.png"), // TODO: change indicator for power ups
+        transform: {
+            let (x, y) = match color {
+                Player::Red => (-4.0, 4.0),
+                Player::Green => (4.0, 4.0),
+                Player::Blue => (4.0, -4.0),
+                Player::Yellow => (-4.0, -4.0),
+            };
+            Transform::from_xyz(x * TILE_SIZE, y * TILE_SIZE, Z_HUMAN_INDICATOR)
+        },
+        ..default()
+    }).id();
+    commands.insert_resource(HumanPlayer{ color, human_indicator });
+    next_state.set(GameState::NextPlayer);
 }
 
 fn show_mask(
@@ -10,12 +10,24 @@ pub struct Die {
     pub timer: Timer,
 }
 
+/// A marble that can't be captured for this many more of its player's turns
+/// (counting the current one).
 #[derive(Component)]
-pub struct Evading;
+pub struct Evading {
+    pub turns: u8,
+}
+
+/// Marks the shield drawn over an evading marble.
+#[derive(Component)]
+pub struct EvadingHighlight;
 
 #[derive(Component)]
 pub struct SelfJumping;
 
+/// Marks the frost over a frozen player's marbles.
+#[derive(Component)]
+pub struct Frozen;
+
 // shared_systems.rs
 /// Used to mark the highlight sprites when a marble is selected, so we can
 /// later remove them when a marble is no longer selected.
@@ -61,7 +73,8 @@ impl Moving {
     /// Creates a new `Moving` component with a destination and origin.
     pub fn new(destination: Vec3, origin: Vec3) -> Self {
         let direction = destination - origin;
-        let dir_norm = direction.normalize();
+        // (a marble dropped right on its destination doesn't need to go anywhere)
+        let dir_norm = direction.normalize_or_zero();
         Self{
             destination,
             direction: Vec2::new(dir_norm.x, dir_norm.y),
@@ -105,6 +118,18 @@ impl Player {
         (i + (rotations + to as usize) * 36) % 48
     }
 
+    /// The player sitting across the board - this is who you're partnered
+    /// with in team play.
+    pub fn partner(&self) -> Player {
+        Player::from((*self as u8 + 2) % 4)
+    }
+
+    /// Returns `true` if `other` is this player's partner and this is a team
+    /// game.
+    pub fn is_teammate(&self, other: Player, team_play: bool) -> bool {
+        team_play && self.partner() == other
+    }
+
     pub fn is_same_index(p1: Player, i1: usize, p2: Player, i2: usize) -> bool {
         if i1 == CENTER_INDEX && i2 == CENTER_INDEX {
             return true;
@@ -160,6 +185,17 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn partner_test() {
+        assert_eq!(Player::Red.partner(), Player::Blue);
+        assert_eq!(Player::Blue.partner(), Player::Red);
+        assert_eq!(Player::Green.partner(), Player::Yellow);
+        assert_eq!(Player::Yellow.partner(), Player::Green);
+        assert!(Player::Red.is_teammate(Player::Blue, true));
+        assert!(!Player::Red.is_teammate(Player::Blue, false));
+        assert!(!Player::Red.is_teammate(Player::Green, true));
+    }
+
     #[test]
     fn shift_index_test() {
         let tests = [
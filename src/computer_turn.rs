@@ -1,9 +1,13 @@
 use bevy::prelude::*;
 use bevy::ecs::event::Events;
+use crate::ai::{choose_target, helpful_power_up, suggest_move};
 use crate::components::*;
 use crate::constants::*;
+use crate::power::PowerEvent;
 use crate::resources::*;
 use crate::shared_systems::HighlightEvent;
+use crate::targeting::{target_kinds, TargetContext};
+use crate::turn_setup::MarbleState;
 use rand::{Rng, thread_rng};
 use rand::seq::IteratorRandom;
 
@@ -35,6 +39,44 @@ pub fn computer_choose_move(
     highlight_events.send(HighlightEvent::On);
 }
 
+/// Uses one of the computer's power-ups instead of the chosen move when it
+/// would help (the move is picked again once it's gone off).
+pub fn computer_use_power_up(
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>)>,
+    dice_data: Res<DiceData>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    mut power_events: EventWriter<PowerEvent>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+) {
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    // doubles are about to be rolled again anyway
+    if current_player_data.possible_moves.is_empty() && settings.rolls_again(&dice_data.dice, player_data) {
+        return;
+    }
+    let owner = current_player_data.marble_owner;
+    let board: Vec<MarbleState> = marbles.iter()
+        .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+        .collect();
+    let suggestion = suggest_move(owner, &board, &current_player_data.possible_moves, &settings);
+    let Some(power_up) = helpful_power_up(owner, &board, &dice_data.dice, player_data, suggestion.as_ref(), &settings) else {
+        return;
+    };
+    // don't bother with a power-up that has nothing to aim at
+    let ctx = TargetContext::new(owner, &board, &game_data, &settings);
+    if !target_kinds(power_up).is_empty() && choose_target(power_up, &[], &ctx).is_none() {
+        return;
+    }
+    let Some(index) = player_data.power_ups.iter().position(|p| p.is_some_and(|(p, _)| p == power_up)) else {
+        return;
+    };
+    current_player_data.selected_marble = None;
+    current_player_data.selected_move = None;
+    highlight_events.send(HighlightEvent::Off);
+    power_events.send(PowerEvent::Use{ player: current_player_data.player, index });
+}
+
 pub fn computer_move_buffer(
     mut computer_turn_timers: ResMut<ComputerTurnTimers>,
     time: Res<Time>,
@@ -44,18 +86,23 @@ pub fn computer_move_buffer(
     mut dice_data: ResMut<DiceData>,
     mut next_state: ResMut<NextState<GameState>>,
     mut highlight_events: EventWriter<HighlightEvent>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
 ) {
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    let roll_again = settings.rolls_again(&dice_data.dice, player_data);
+
     // if the player rolled doubles we know they're going to roll again, but if
     // they used at least one of the dice for a move then we don't need to wait
     // for the buffer time to roll the dice again (we already waited when they
     // used their dice)
     let no_moves = current_player_data.possible_moves.is_empty();
-    if no_moves && dice_data.dice.doubles && dice_data.dice.did_use_any() {
+    if no_moves && roll_again && dice_data.dice.did_use_any() {
         next_state.set(GameState::DiceRoll);
         return;
     }
 
-    let timer_finished = if no_moves && (dice_data.dice.doubles || dice_data.dice.did_use_any()) {
+    let timer_finished = if no_moves && (roll_again || dice_data.dice.did_use_any()) {
         computer_turn_timers.buffer_timer.tick(time.delta()).just_finished()
     } else {
         computer_turn_timers.move_timer.tick(time.delta()).just_finished()
@@ -67,14 +114,14 @@ pub fn computer_move_buffer(
             dice_data.use_die(which, &mut commands);
             let destination = {
                 let (c, r) = BOARD[destination];
-                let (x, y) = current_player_data.player.rotate_coords((c as f32, r as f32));
+                let (x, y) = current_player_data.marble_owner.rotate_coords((c as f32, r as f32));
                 Vec3::new(x * TILE_SIZE, y * TILE_SIZE, Z_MARBLE)
             };
             commands.entity(entity).insert(Moving::new(destination, transform.translation));
             current_player_data.move_marble();
             highlight_events.send(HighlightEvent::Off);
             next_state.set(GameState::WaitForAnimation);
-        } else if dice_data.dice.doubles {
+        } else if roll_again {
             next_state.set(GameState::DiceRoll);
         } else {
             next_state.set(GameState::EndTurn);
@@ -17,6 +17,9 @@ pub const START_INDEX: usize = 0;
 pub const CENTER_INDEX: usize = 53;
 pub const FIRST_HOME_INDEX: usize = 48;
 pub const LAST_HOME_INDEX: usize = 52;
+// vexation.rs
+pub const MIN_MARBLES: usize = 3;
+pub const MAX_MARBLES: usize = LAST_HOME_INDEX - FIRST_HOME_INDEX + 1;
 // turn_setup.rs
 pub const CENTER_ENTRANCE_INDEXES: [usize; 3] = [5, 17, 29];
 // turn_setup.rs
@@ -1,25 +1,42 @@
 use bevy::prelude::*;
 use crate::components::*;
+use crate::constants::*;
+use crate::notice::NoticeEvent;
 use crate::resources::*;
+use crate::shared_systems::SharedSystemSet;
 use rand::{Rng, thread_rng};
 use rand::distributions::Uniform;
 
+/// Sent when a player rolls their third doubles in a row with the
+/// [`DoublesRule::ThreeStrikes`] rule - their lead marble goes back to base.
+#[derive(Debug, Event)]
+pub struct DoublesPenaltyEvent(pub Player);
+
+/// Marks the warning shown between the dice after a second doubles in a row.
+#[derive(Component)]
+struct DoublesWarning;
+
 pub struct DiceRollPlugin;
 
 impl Plugin for DiceRollPlugin {
     fn build(&self, app: &mut App) {
         app  
+            .add_event::<DoublesPenaltyEvent>()
+
             // dice roll
             .add_systems(
                 OnEnter(GameState::DiceRoll),
-                (undim_dice, roll_dice),
+                (undim_dice, clear_doubles_warning, roll_dice),
             )
             .add_systems(Update, roll_animation
                 .run_if(in_state(GameState::DiceRoll))
             )
             .add_systems(OnExit(GameState::DiceRoll),
-                stop_roll_animation
+                (stop_roll_animation, show_doubles_warning)
             )
+            .add_systems(OnEnter(GameState::EndTurn), clear_doubles_warning)
+            .add_systems(OnEnter(GameState::GameEnd), clear_doubles_warning)
+            .add_systems(Update, send_lead_marble_home.in_set(SharedSystemSet))
         ;
     }
 }
@@ -42,10 +59,10 @@ fn roll_die() -> u8 {
 
 fn roll_dice(
     mut dice_data: ResMut<DiceData>,
-    game_data: Res<GameData>,
+    mut game_data: ResMut<GameData>,
     current_player_data: Res<CurrentPlayerData>,
 ) {
-    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    let player_data = game_data.players.get_mut(&current_player_data.player).unwrap();
     let (d1, d2) = loop {
         let (a, b) = (roll_die(), roll_die());
         // before accepting the roll, make sure the player get's a move if they
@@ -55,6 +72,9 @@ fn roll_dice(
         }
     };
     dice_data.dice = Dice::new(d1, d2);
+    if dice_data.dice.doubles {
+        player_data.consecutive_doubles += 1;
+    }
 }
 
 fn roll_animation(
@@ -62,6 +82,11 @@ fn roll_animation(
     mut roll_animation_timer: ResMut<RollAnimationTimer>,
     mut query: Query<(&mut Die, &mut Transform, &mut TextureAtlasSprite)>,
     mut next_state: ResMut<NextState<GameState>>,
+    game_data: Res<GameData>,
+    current_player_data: Res<CurrentPlayerData>,
+    settings: Res<GameSettings>,
+    mut penalty_events: EventWriter<DoublesPenaltyEvent>,
+    mut notice_events: EventWriter<NoticeEvent>,
 ) {
     const DIE_MOVE_SPEED: f32 = 500.;
 
@@ -88,7 +113,17 @@ fn roll_animation(
 
     if roll_animation_timer.0.tick(time.delta()).just_finished() {
         roll_animation_timer.0.reset();
-        next_state.set(GameState::TurnSetup);
+        let player_data = game_data.players.get(&current_player_data.player).unwrap();
+        if player_data.power_up_status.frozen_turns > 0 {
+            // the roll is still shown so it's clear what was missed
+            notice_events.send(NoticeEvent(format!("{:?} is frozen - turn skipped", current_player_data.player)));
+            next_state.set(GameState::EndTurn);
+        } else if settings.doubles == DoublesRule::ThreeStrikes && player_data.consecutive_doubles >= 3 {
+            penalty_events.send(DoublesPenaltyEvent(current_player_data.marble_owner));
+            next_state.set(GameState::EndTurn);
+        } else {
+            next_state.set(GameState::TurnSetup);
+        }
     }
 
     // TODO: create a 'roll buffer' timer so after the 'roll timer' stops, we have a second to see what the dice roll was before letting the player pick a move
@@ -106,3 +141,70 @@ fn stop_roll_animation(
     sprite.index = (dice_data.dice.two.unwrap() - 1) as usize;
     transform.rotation = Quat::from_rotation_z(0.0);
 }
+
+/// Shows a warning between the dice when the next doubles will cost the player
+/// their lead marble.
+fn show_doubles_warning(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    dice_data: Res<DiceData>,
+    dice: Query<&Die>,
+    game_data: Res<GameData>,
+    current_player_data: Res<CurrentPlayerData>,
+    settings: Res<GameSettings>,
+) {
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    if settings.doubles != DoublesRule::ThreeStrikes || player_data.consecutive_doubles != 2 {
+        return;
+    }
+    let (Ok(die_1), Ok(die_2)) = (dice.get(dice_data.die_1), dice.get(dice_data.die_2)) else {
+        return;
+    };
+    // the dice are always two tiles apart so this lands in the gap between them
+    let between = (die_1.location + die_2.location) / 2.0;
+    commands.spawn((
+        Text2dBundle{
+            text: Text::from_section(
+                "!",
+                TextStyle{
+                    font: asset_server.load("Kenney Mini.ttf"),
+                    font_size: 32.0,
+                    color: Color::rgb(1.0, 0.5, 0.1),
+                },
+            ),
+            transform: Transform::from_xyz(between.x, between.y, Z_DICE),
+            ..default()
+        },
+        DoublesWarning,
+    ));
+}
+
+fn clear_doubles_warning(
+    mut commands: Commands,
+    warnings: Query<Entity, With<DoublesWarning>>,
+) {
+    for warning in &warnings {
+        commands.entity(warning).despawn();
+    }
+}
+
+/// Sends the player's marble that's furthest along the main track (or in the
+/// center) back to its base.
+fn send_lead_marble_home(
+    mut commands: Commands,
+    mut penalty_events: EventReader<DoublesPenaltyEvent>,
+    mut marbles: Query<(Entity, &mut Marble, &Transform, &Player)>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    for DoublesPenaltyEvent(player) in penalty_events.iter() {
+        let lead = marbles.iter_mut()
+            .filter(|(_, m, _, p)| *p == player && (m.index < FIRST_HOME_INDEX || m.index == CENTER_INDEX))
+            // a marble in the center is as far along as the center exit
+            .max_by_key(|(_, m, _, _)| if m.index == CENTER_INDEX { CENTER_EXIT_INDEX } else { m.index });
+        if let Some((entity, mut marble, transform, _)) = lead {
+            marble.index = BOARD.len();
+            commands.entity(entity).insert(Moving::new(marble.origin, transform.translation));
+        }
+        notice_events.send(NoticeEvent(format!("Third doubles - {:?} loses their lead marble", player)));
+    }
+}
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use crate::components::{Evading, Player};
 use crate::power::PowerDownEvent;
 use crate::resources::{CurrentPlayerData, GameData, GameState, PowerDownType};
 
@@ -7,15 +8,21 @@ pub fn end_turn(
     mut game_data: ResMut<GameData>,
     mut power_down_events: EventWriter<PowerDownEvent>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut evading: Query<(Entity, &mut Evading, &Player)>,
 ) {
     let player_data = game_data.players.get_mut(&current_player_data.player).unwrap();
-    if let Some(power_down) = player_data.end_of_turn() {
-        match power_down {
-            PowerDownType::Evading => power_down_events.send(PowerDownEvent::Evading(current_player_data.player)),
-            PowerDownType::SelfJumping => power_down_events.send(PowerDownEvent::SelfJumping(current_player_data.player)),
-            PowerDownType::EvadingAndSelfJumping => {
-                power_down_events.send(PowerDownEvent::Evading(current_player_data.player));
-                power_down_events.send(PowerDownEvent::SelfJumping(current_player_data.player));
+    for power_down in player_data.end_of_turn() {
+        power_down_events.send(match power_down {
+            PowerDownType::SelfJumping => PowerDownEvent::SelfJumping(current_player_data.player),
+            PowerDownType::Frozen => PowerDownEvent::Frozen(current_player_data.player),
+        });
+    }
+    // each evading marble counts down its own player's turns
+    for (marble, mut evading, player) in &mut evading {
+        if *player == current_player_data.player {
+            evading.turns = evading.turns.saturating_sub(1);
+            if evading.turns == 0 {
+                power_down_events.send(PowerDownEvent::Evading(marble));
             }
         }
     }
@@ -1,19 +1,60 @@
 use bevy::prelude::*;
 use bevy::input::mouse::{MouseButtonInput, MouseButton};
 use bevy::window::PrimaryWindow;
+use crate::ai::{helpful_power_up, suggest_move};
 use crate::buttons::*;
 use crate::components::*;
 use crate::constants::*;
-use crate::power::PowerEvent;
+use crate::input::{Bindings, InputAction};
+use crate::notice::NoticeEvent;
+use crate::power::{discarding, DiscardPrompt, PowerBar, PowerEvent, PowerUpDrafts};
 use crate::shared_systems::HighlightEvent;
 use crate::resources::*;
+use crate::turn_setup::{move_path, MarbleState};
 
 #[derive(Debug, Event)]
 struct ClickEvent(pub Vec2);
 
+impl ClickEvent {
+    /// Clicks are in board coordinates, so this turns them back into a cursor
+    /// position for `is_in_bounds`.
+    fn cursor_position(&self) -> Vec2 {
+        Vec2::new(self.0.x + WINDOW_SIZE / 2.0, -(self.0.y - WINDOW_SIZE / 2.0))
+    }
+}
+
 #[derive(Event)]
 struct MoveEvent(pub (Entity, usize, WhichDie, Vec3));
 
+/// Part of the preview of the move under the cursor.
+#[derive(Component)]
+struct MovePreview;
+
+/// A move that can be made with different dice - the player has to pick which
+/// dice to use from the popup before it's made.
+#[derive(Resource)]
+struct DieChoice {
+    marble: Entity,
+    destination: usize,
+    position: Vec3,
+}
+
+/// Part of the popup for picking the dice.
+#[derive(Component)]
+struct DieChoicePopup;
+
+/// A marble being dragged by the mouse (or dropped on a tile and waiting for
+/// the player to pick which dice to use). It goes back to `start` if the drop
+/// doesn't turn into a move.
+#[derive(Component)]
+struct Dragged {
+    start: Vec3,
+}
+
+/// How far the mouse has to move while pressed on a marble before it's
+/// dragged instead of just clicked.
+const DRAG_DISTANCE: f32 = TILE_SIZE / 4.0;
+
 pub struct HumanTurnPlugin;
 
 impl Plugin for HumanTurnPlugin {
@@ -22,31 +63,35 @@ impl Plugin for HumanTurnPlugin {
             .add_event::<ClickEvent>()
             .add_event::<MoveEvent>()
 
-            .add_systems(OnEnter(GameState::HumanTurn), enable_ui)
-            // ui
+            .add_systems(OnEnter(GameState::HumanTurn), (enable_ui, lock_done_button, lock_undo_button, reset_auto_play_timer).chain())
+            // a new roll or a new turn means the last move can no longer be taken back
+            .add_systems(OnEnter(GameState::DiceRoll), clear_undo)
+            .add_systems(OnEnter(GameState::EndTurn), clear_undo)
+            // ui (the discard prompt has to be answered first)
             .add_systems(Update,
-                (execute_button_actions, mouse_watcher::<GameButtonAction>, watch_button_state_changes).chain()
-                .run_if(in_state(GameState::HumanTurn))
+                (execute_button_actions, undo_move, show_hint, choose_die, mouse_watcher::<GameButtonAction>, watch_button_state_changes, watch_text_button_state_changes).chain()
+                .run_if(in_state(GameState::HumanTurn).and_then(not(discarding)))
             )
             // game play
             .add_systems(Update,
-                (translate_mouse_input, interpret_click_event, move_event_handler).chain()
-                .run_if(in_state(GameState::HumanTurn))
+                (translate_mouse_input, drag_marble, explain_disabled_buttons, interpret_click_event, keyboard_turn_control, auto_play, move_event_handler, preview_hovered_move).chain()
+                .run_if(in_state(GameState::HumanTurn).and_then(not(discarding)))
             )
-            .add_systems(OnExit(GameState::HumanTurn), disable_ui)
+            .add_systems(OnExit(GameState::HumanTurn), (disable_ui, clear_move_preview, close_die_choice))
             ;
     }
 }
 
 fn enable_ui(
     mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
     windows: Query<&Window, With<PrimaryWindow>>,
     mut button_query: Query<(&mut ButtonState, &mut TextureAtlasSprite, &Transform)>,
 ) {
     let Ok(w) = windows.get_single() else {
         return;
     };
-    let mouse_pressed = mouse_button_inputs.pressed(MouseButton::Left);
+    let mouse_pressed = mouse_button_inputs.pressed(bindings.click);
 
     for (mut button_state, mut button_sprite, button_transform) in &mut button_query {
         *button_state = get_button_state(w.cursor_position(), button_transform.translation, UI_BUTTON_SIZE.clone(), mouse_pressed);
@@ -54,23 +99,137 @@ fn enable_ui(
     }
 }
 
+/// Keeps the done button disabled while the "must use both dice" rule says the
+/// player still has dice to use.
+fn lock_done_button(
+    mut commands: Commands,
+    settings: Res<GameSettings>,
+    current_player_data: Res<CurrentPlayerData>,
+    mut button_query: Query<(Entity, &ButtonAction<GameButtonAction>, &mut TextureAtlasSprite, &mut ButtonState)>,
+) {
+    let locked = settings.use_both_dice && current_player_data.has_dice_moves();
+    for (entity, action, mut sprite, mut state) in &mut button_query {
+        if !matches!(action.0.0, GameButtonAction::Done) {
+            continue;
+        }
+        if locked {
+            commands.entity(entity).insert(Disabled);
+            sprite.color = Color::rgba(1.0, 1.0, 1.0, 0.4);
+            sprite.index = 0;
+            *state = ButtonState::NotHovered;
+        } else {
+            commands.entity(entity).remove::<Disabled>();
+        }
+    }
+}
+
+/// Disables the undo button when there's no move to take back.
+fn lock_undo_button(
+    mut commands: Commands,
+    undo_data: Res<UndoData>,
+    mut button_query: Query<(Entity, &ButtonAction<GameButtonAction>, &mut ButtonState), With<TextButton>>,
+) {
+    for (entity, action, mut state) in &mut button_query {
+        if !matches!(action.0.0, GameButtonAction::Undo) {
+            continue;
+        }
+        if undo_data.0.is_some() {
+            commands.entity(entity).remove::<Disabled>();
+        } else {
+            commands.entity(entity).insert(Disabled);
+        }
+        // always flag a change so the text color catches up with the disabled state
+        *state = ButtonState::NotHovered;
+    }
+}
+
+fn reset_auto_play_timer(
+    mut buffer_timer: ResMut<BufferTimer>,
+) {
+    buffer_timer.0.reset();
+}
+
+/// Plays the human player's only move, or ends their turn when they have no
+/// moves, after a short pause (only when those settings are on). A player
+/// holding a power-up might want to use it first, so they're left alone.
+fn auto_play(
+    time: Res<Time>,
+    mut buffer_timer: ResMut<BufferTimer>,
+    settings: Res<GameSettings>,
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    game_data: Res<GameData>,
+    dice_data: Res<DiceData>,
+    die_choice: Option<Res<DieChoice>>,
+    dragged: Query<(), With<Dragged>>,
+    mut move_events: EventWriter<MoveEvent>,
+    mut action_events: EventWriter<ActionEvent<GameButtonAction>>,
+    mut notice_events: EventWriter<NoticeEvent>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+) {
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    let auto = match current_player_data.possible_moves.len() {
+        0 => settings.auto_pass,
+        1 => settings.auto_forced_move,
+        _ => false,
+    };
+    if !auto || player_data.power_ups.iter().any(|p| p.is_some()) || die_choice.is_some() || !dragged.is_empty() {
+        return;
+    }
+    if !buffer_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    match current_player_data.possible_moves.first().copied() {
+        Some((marble, MarbleMove{ destination, which, .. })) => {
+            let (x, y) = current_player_data.marble_owner.rotate_coords((BOARD[destination].0 as f32, BOARD[destination].1 as f32));
+            notice_events.send(NoticeEvent("Only one legal move - played it for you".to_string()));
+            current_player_data.move_marble();
+            move_events.send(MoveEvent((marble, destination, which, Vec3::new(x * TILE_SIZE, y * TILE_SIZE, Z_MARBLE))));
+            highlight_events.send(HighlightEvent::Off);
+        }
+        None => {
+            // there's nothing to say when the dice have all been used
+            if !dice_data.dice.is_empty() {
+                let notice = if settings.rolls_again(&dice_data.dice, player_data) {
+                    "No legal moves - rolling again"
+                } else {
+                    "No legal moves - turn passed"
+                };
+                notice_events.send(NoticeEvent(notice.to_string()));
+            }
+            action_events.send(ActionEvent(GameButtonAction::Done));
+        }
+    }
+}
+
+fn clear_undo(
+    mut undo_data: ResMut<UndoData>,
+) {
+    undo_data.0 = None;
+}
+
 fn disable_ui(
     mut button_query: Query<(&mut TextureAtlasSprite, &mut ButtonState)>,
+    mut text_button_query: Query<&mut ButtonState, (With<TextButton>, Without<TextureAtlasSprite>)>,
 ) {
     for (mut sprite, mut state) in &mut button_query {
         sprite.color = Color::rgba(1.0, 1.0, 1.0, 0.4);
         sprite.index = 0;
         *state = ButtonState::NotHovered;
     }
+    for mut state in &mut text_button_query {
+        *state = ButtonState::NotHovered;
+    }
 }
 
 fn translate_mouse_input(
     windows: Query<&Window, With<PrimaryWindow>>,
     mut mouse_button_input_events: EventReader<MouseButtonInput>,
+    bindings: Res<Bindings>,
     mut click_events: EventWriter<ClickEvent>,
 ) {
     if mouse_button_input_events.iter()
-        .filter(|e| e.button == MouseButton::Left && e.state.is_pressed())
+        .filter(|e| e.button == bindings.click && e.state.is_pressed())
         .last().is_some()
     {
         let Some(pos) = windows.get_single().map_or(None, |w| w.cursor_position()) else {
@@ -81,14 +240,138 @@ fn translate_mouse_input(
     }
 }
 
+/// Lets the player drag one of their marbles onto a tile instead of clicking
+/// the marble and then the tile. Pressing on the marble selects it (so its
+/// destinations light up), and dropping it on one of those destinations is
+/// treated like clicking that tile. Dropping it anywhere else puts it back.
+fn drag_marble(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    current_player_data: Res<CurrentPlayerData>,
+    mut marbles: Query<(Entity, &mut Transform, Option<&Dragged>), (With<Marble>, With<CurrentPlayer>)>,
+    die_choice: Option<Res<DieChoice>>,
+    mut click_events: EventWriter<ClickEvent>,
+    mut pressed: Local<Option<(Entity, Vec2)>>,
+) {
+    let window_cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let cursor = window_cursor.map(|pos| Vec2::new(pos.x - WINDOW_SIZE / 2.0, -(pos.y - WINDOW_SIZE / 2.0)));
+    // a dropped marble waiting on the dice popup can't be picked back up
+    if die_choice.is_some() {
+        *pressed = None;
+        return;
+    }
+    if mouse_button_inputs.just_pressed(bindings.click) {
+        *pressed = window_cursor.zip(cursor).and_then(|(window_cursor, cursor)| marbles.iter()
+            .filter(|(e, _, _)| !current_player_data.get_moves(*e).is_empty())
+            .find(|(_, t, _)| is_in_bounds(window_cursor, t.translation, TILE_BUTTON_SIZE))
+            .map(|(e, _, _)| (e, cursor))
+        );
+    }
+    let Some((entity, from)) = *pressed else {
+        return;
+    };
+    let Ok((_, mut transform, dragged)) = marbles.get_mut(entity) else {
+        *pressed = None;
+        return;
+    };
+
+    if mouse_button_inputs.pressed(bindings.click) {
+        let Some(cursor) = cursor else {
+            return;
+        };
+        if dragged.is_none() {
+            if cursor.distance(from) < DRAG_DISTANCE {
+                return;
+            }
+            commands.entity(entity).insert(Dragged{ start: transform.translation });
+        }
+        // keep the marble above everything else while it's dragged
+        transform.translation = cursor.extend(Z_UI);
+        return;
+    }
+
+    // the mouse was released
+    *pressed = None;
+    let Some(Dragged{ start }) = dragged else {
+        return;
+    };
+    let owner = current_player_data.marble_owner;
+    let drop = cursor.and_then(|cursor| {
+        let (col, row) = (snap(cursor.x), snap(cursor.y));
+        BOARD.into_iter()
+            .position(|(x, y)| owner.rotate_coords((x as f32, y as f32)) == (col / TILE_SIZE, row / TILE_SIZE))
+            .filter(|index| current_player_data.get_moves(entity).iter().any(|m| m.destination == *index))
+            .map(|_| (cursor, Vec2::new(col, row)))
+    });
+    if let Some((cursor, tile)) = drop {
+        // the move slides the marble from where it was dropped onto the tile
+        transform.translation = cursor.extend(Z_MARBLE);
+        click_events.send(ClickEvent(tile));
+    } else {
+        transform.translation = *start;
+        commands.entity(entity).remove::<Dragged>();
+    }
+}
+
+/// Puts a dropped marble back where it was picked up from.
+fn return_dragged_marbles(
+    commands: &mut Commands,
+    dragged: &mut Query<(Entity, &Dragged, &mut Transform)>,
+) {
+    for (entity, Dragged{ start }, mut transform) in dragged {
+        transform.translation = *start;
+        commands.entity(entity).remove::<Dragged>();
+    }
+}
+
+/// Lets the player know why clicking a disabled button doesn't do anything.
+fn explain_disabled_buttons(
+    mut click_events: EventReader<ClickEvent>,
+    button_query: Query<(&ButtonAction<GameButtonAction>, &Transform, &ButtonSize), With<Disabled>>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    for click_event in click_events.iter() {
+        for (action, transform, size) in &button_query {
+            if !is_in_bounds(click_event.cursor_position(), transform.translation, size.0) {
+                continue;
+            }
+            match action.0.0 {
+                GameButtonAction::Done => notice_events.send(NoticeEvent("Use your dice before ending your turn".to_string())),
+                GameButtonAction::Undo => notice_events.send(NoticeEvent("There is no move to undo".to_string())),
+                _ => {}
+            }
+        }
+    }
+}
+
 fn interpret_click_event(
+    mut commands: Commands,
     mut highlight_events: EventWriter<HighlightEvent>,
     mut move_events: EventWriter<MoveEvent>,
     mut click_events: EventReader<ClickEvent>,
     mut current_player_data: ResMut<CurrentPlayerData>,
-    marbles_query: Query<(Entity, &Transform), (With<Marble>, With<CurrentPlayer>)>,
+    marbles_query: Query<(Entity, &Transform), (With<Marble>, With<CurrentPlayer>, Without<Dragged>)>,
+    mut dragged: Query<(Entity, &Dragged, &mut Transform)>,
+    die_choice: Option<Res<DieChoice>>,
+    popup: Query<Entity, With<DieChoicePopup>>,
+    popup_buttons: Query<(&Transform, &ButtonSize), With<DieChoicePopup>>,
+    dice_data: Res<DiceData>,
+    asset_server: Res<AssetServer>,
 ) {
     if let Some(click_event) = click_events.iter().last() {
+        // while the player is picking dice, clicking anywhere but the popup closes it
+        if die_choice.is_some() {
+            let on_popup = popup_buttons.iter()
+                .any(|(t, size)| is_in_bounds(click_event.cursor_position(), t.translation, size.0));
+            if !on_popup {
+                commands.remove_resource::<DieChoice>();
+                popup.for_each(|e| commands.entity(e).despawn());
+                return_dragged_marbles(&mut commands, &mut dragged);
+            }
+            return;
+        }
         // interpret click as marble selection
         if let Some(marble) = marbles_query.iter().find_map(|(e, t)| {
                 let found = click_event.0.x > t.translation.x - TILE_SIZE / 2.0 && // to the right of the left edge
@@ -104,25 +387,39 @@ fn interpret_click_event(
                 }
             }
             current_player_data.selected_marble = Some(marble);
+            current_player_data.selected_move = None; // drop any hinted move
             highlight_events.send(HighlightEvent::On);
         }
         // interpret click as move selection
         else if let Some(marble) = current_player_data.selected_marble {
             // to compare to board coordinates, we need to snap the click event to the center of a tile
             let (col, row) = (snap(click_event.0.x), snap(click_event.0.y));
-            // find the move that corresponds to this click position
-            let selected_move = match BOARD.into_iter().position(|(x, y)| {
+            // find the moves that correspond to this click position
+            let mut selected_moves: Vec<MarbleMove> = match BOARD.into_iter().position(|(x, y)| {
                 // rotate the board coordinates based on the current player
-                let rot = current_player_data.player.rotate_coords((x as f32, y as f32));
+                let rot = current_player_data.marble_owner.rotate_coords((x as f32, y as f32));
                 // find the board index that matches the click position
                 rot == (col / TILE_SIZE, row / TILE_SIZE)
             }) {
-                // find a move for this board index
+                // find the moves for this board index
                 Some(clicked_board_index) => current_player_data
-                    .get_moves(marble).into_iter().find(|MarbleMove{ destination, .. }| *destination == clicked_board_index),
-                _ => None,
+                    .get_moves(marble).into_iter().filter(|MarbleMove{ destination, .. }| *destination == clicked_board_index).collect(),
+                _ => Vec::new(),
             };
-            if let Some(MarbleMove{ destination, which, .. }) = selected_move {
+            selected_moves.sort_by_key(|m| m.which);
+            selected_moves.dedup_by_key(|m| m.which);
+            // a hinted move already knows which dice to use
+            if let Some(hinted) = current_player_data.selected_move.filter(|h| selected_moves.iter().any(|m| m.destination == h.destination)) {
+                selected_moves = vec![hinted];
+            }
+            if selected_moves.len() > 1 {
+                // the same tile can be reached with different dice so let the player pick
+                let position = Vec3::new(col, row, Z_MARBLE);
+                open_die_choice(&mut commands, &selected_moves, &dice_data.dice, position, asset_server.load("Kenney Mini.ttf"));
+                commands.insert_resource(DieChoice{ marble, destination: selected_moves[0].destination, position });
+                return;
+            }
+            if let Some(MarbleMove{ destination, which, .. }) = selected_moves.pop() {
                 current_player_data.move_marble();
                 move_events.send(MoveEvent((marble, destination, which, Vec3::new(col, row, Z_MARBLE))));
             } else {
@@ -138,18 +435,218 @@ fn interpret_click_event(
     }
 }
 
+/// Lets the human player take their turn with the keyboard. Next/previous
+/// cycle through the marbles that can move, the target keys cycle through the
+/// selected marble's moves, and confirm makes the chosen move. The buttons
+/// have their own keys.
+fn keyboard_turn_control(
+    mut commands: Commands,
+    mut input_actions: EventReader<InputAction>,
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    marbles: Query<(Entity, &Marble), With<CurrentPlayer>>,
+    buttons: Query<(&ButtonAction<GameButtonAction>, Option<&Disabled>)>,
+    popup: Query<Entity, With<DieChoicePopup>>,
+    mut dragged: Query<(Entity, &Dragged, &mut Transform)>,
+    game_data: Res<GameData>,
+    drafts: Res<PowerUpDrafts>,
+    discard_prompt: Option<Res<DiscardPrompt>>,
+    mut move_events: EventWriter<MoveEvent>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+    mut action_events: EventWriter<ActionEvent<GameButtonAction>>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    for input_action in input_actions.iter() {
+        match *input_action {
+            InputAction::Next | InputAction::Previous => {
+                // the marbles that can move, in the order they sit on the board
+                let mut movable: Vec<(Entity, usize)> = marbles.iter()
+                    .filter(|(e, _)| current_player_data.possible_moves.iter().any(|(m, _)| m == e))
+                    .map(|(e, m)| (e, m.index))
+                    .collect();
+                if movable.is_empty() {
+                    continue;
+                }
+                movable.sort_by_key(|(e, index)| (*index, *e));
+                let next = match movable.iter().position(|(e, _)| Some(*e) == current_player_data.selected_marble) {
+                    Some(i) if *input_action == InputAction::Next => (i + 1) % movable.len(),
+                    Some(i) => (i + movable.len() - 1) % movable.len(),
+                    None => 0,
+                };
+                current_player_data.selected_marble = Some(movable[next].0);
+                current_player_data.selected_move = None;
+                highlight_events.send(HighlightEvent::On);
+            }
+            InputAction::NextTarget | InputAction::PreviousTarget => {
+                let Some(marble) = current_player_data.selected_marble else {
+                    continue;
+                };
+                let mut moves = current_player_data.get_moves(marble);
+                if moves.is_empty() {
+                    continue;
+                }
+                moves.sort_by_key(|m| (m.distance, m.which));
+                let next = match moves.iter().position(|m| current_player_data.selected_move.is_some_and(|s| s.destination == m.destination && s.which == m.which)) {
+                    Some(i) if *input_action == InputAction::NextTarget => (i + 1) % moves.len(),
+                    Some(i) => (i + moves.len() - 1) % moves.len(),
+                    None => 0,
+                };
+                current_player_data.select_move((marble, moves[next]));
+                highlight_events.send(HighlightEvent::On);
+            }
+            InputAction::Confirm => {
+                if let Some((marble, MarbleMove{ destination, which, .. })) = current_player_data.get_selected_move() {
+                    let (x, y) = current_player_data.marble_owner.rotate_coords((BOARD[destination].0 as f32, BOARD[destination].1 as f32));
+                    commands.remove_resource::<DieChoice>();
+                    popup.for_each(|e| commands.entity(e).despawn());
+                    current_player_data.move_marble();
+                    move_events.send(MoveEvent((marble, destination, which, Vec3::new(x * TILE_SIZE, y * TILE_SIZE, Z_MARBLE))));
+                    highlight_events.send(HighlightEvent::Off);
+                }
+            }
+            InputAction::Cancel => {
+                commands.remove_resource::<DieChoice>();
+                popup.for_each(|e| commands.entity(e).despawn());
+                return_dragged_marbles(&mut commands, &mut dragged);
+                current_player_data.selected_marble = None;
+                current_player_data.selected_move = None;
+                highlight_events.send(HighlightEvent::Off);
+            }
+            InputAction::Done => {
+                if buttons.iter().any(|(a, d)| d.is_some() && matches!(a.0.0, GameButtonAction::Done)) {
+                    notice_events.send(NoticeEvent("Use your dice before ending your turn".to_string()));
+                } else {
+                    action_events.send(ActionEvent(GameButtonAction::Done));
+                }
+            }
+            InputAction::Undo => {
+                if buttons.iter().any(|(a, d)| d.is_some() && matches!(a.0.0, GameButtonAction::Undo)) {
+                    notice_events.send(NoticeEvent("There is no move to undo".to_string()));
+                } else {
+                    action_events.send(ActionEvent(GameButtonAction::Undo));
+                }
+            }
+            InputAction::Hint => action_events.send(ActionEvent(GameButtonAction::Hint)),
+            // the power-up keys pick from the draft (or what to discard) while one is being offered
+            InputAction::PowerUp(_) if !drafts.0.is_empty() || discard_prompt.is_some() => {}
+            InputAction::PowerUp(slot) => {
+                let player = current_player_data.player;
+                if game_data.players.get(&player).unwrap().power_ups.get(slot).is_some_and(|p| p.is_some()) {
+                    action_events.send(ActionEvent(match slot {
+                        0 => GameButtonAction::PowerUpOne(player),
+                        1 => GameButtonAction::PowerUpTwo(player),
+                        _ => GameButtonAction::PowerUpThree(player),
+                    }));
+                }
+            }
+        }
+    }
+}
+
+/// Opens a popup next to the clicked tile with one button for each way of
+/// using the dice to get there.
+fn open_die_choice(
+    commands: &mut Commands,
+    moves: &[MarbleMove],
+    dice: &Dice,
+    position: Vec3,
+    font: Handle<Font>,
+) {
+    let button_size = Vec2::new(TILE_SIZE * 9.0, TILE_SIZE);
+    let height = button_size.y * moves.len() as f32;
+    // keep the popup above the tile, but on the board
+    let x = position.x.clamp(-WINDOW_SIZE / 2.0 + button_size.x / 2.0, WINDOW_SIZE / 2.0 - button_size.x / 2.0);
+    let top = (position.y + TILE_SIZE + height).min(WINDOW_SIZE / 2.0);
+    commands.spawn((
+        SpriteBundle{
+            sprite: Sprite{
+                color: Color::rgba(0.0, 0.0, 0.0, 0.8),
+                custom_size: Some(Vec2::new(button_size.x, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, top - height / 2.0, Z_UI),
+            ..default()
+        },
+        DieChoicePopup,
+    ));
+    for (i, m) in moves.iter().enumerate() {
+        let y = top - button_size.y * (i as f32 + 0.5);
+        commands.spawn((
+            text_button_bundle(
+                format!("use {}", die_label(m.which, dice)).to_uppercase(),
+                font.clone(),
+                Transform::from_xyz(x, y, Z_UI + 0.1),
+                ButtonAction(ActionEvent(GameButtonAction::ChooseDie(m.which))),
+                ButtonState::NotHovered,
+                ButtonSize(button_size),
+            ),
+            DieChoicePopup,
+        ));
+    }
+}
+
+/// Makes the move once the player has picked which dice to use.
+fn choose_die(
+    mut commands: Commands,
+    mut action_events: EventReader<ActionEvent<GameButtonAction>>,
+    die_choice: Option<Res<DieChoice>>,
+    popup: Query<Entity, With<DieChoicePopup>>,
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    mut move_events: EventWriter<MoveEvent>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+) {
+    let Some(which) = action_events.iter().find_map(|action| match action.0 {
+        GameButtonAction::ChooseDie(which) => Some(which),
+        _ => None,
+    }) else {
+        return;
+    };
+    let Some(choice) = die_choice else {
+        return;
+    };
+    commands.remove_resource::<DieChoice>();
+    popup.for_each(|e| commands.entity(e).despawn());
+    current_player_data.move_marble();
+    move_events.send(MoveEvent((choice.marble, choice.destination, which, choice.position)));
+    highlight_events.send(HighlightEvent::Off);
+}
+
+fn close_die_choice(
+    mut commands: Commands,
+    popup: Query<Entity, With<DieChoicePopup>>,
+    mut dragged: Query<(Entity, &Dragged, &mut Transform)>,
+) {
+    commands.remove_resource::<DieChoice>();
+    popup.for_each(|e| commands.entity(e).despawn());
+    return_dragged_marbles(&mut commands, &mut dragged);
+}
+
 fn move_event_handler(
     mut commands: Commands,
     mut move_events: EventReader<MoveEvent>,
     mut marbles: Query<(Entity, &Transform, &mut Marble), With<CurrentPlayer>>,
     mut dice_data: ResMut<DiceData>,
     mut next_state: ResMut<NextState<GameState>>,
+    power_bars: Query<(&PowerBar, &Player)>,
+    game_data: Res<GameData>,
+    current_player_data: Res<CurrentPlayerData>,
+    mut undo_data: ResMut<UndoData>,
 ) {
     if let Some(MoveEvent((e, idx, which, dest))) = move_events.iter().last() {
         let (e, t, mut m) = marbles.get_mut(*e).unwrap();
+        // remember how things were before the move so it can be taken back
+        undo_data.0 = Some(UndoMove{
+            marble: e,
+            index: m.index,
+            prev_index: m.prev_index,
+            captured: None,
+            dice: dice_data.dice.clone(),
+            power_bars: power_bars.iter().map(|(bar, player)| (*player, bar.power, bar.power_up_count)).collect(),
+            power_ups: game_data.players.iter().map(|(player, data)| (*player, data.power_ups)).collect(),
+            power_up_status: game_data.players.get(&current_player_data.player).unwrap().power_up_status.clone(),
+        });
         m.update_index(*idx);
         dice_data.use_die(*which, &mut commands);
-        commands.entity(e).insert(Moving::new(*dest, t.translation));
+        commands.entity(e).insert(Moving::new(*dest, t.translation)).remove::<Dragged>();
         next_state.set(GameState::WaitForAnimation);
     }
 }
@@ -159,26 +656,271 @@ fn execute_button_actions(
     mut next_state: ResMut<NextState<GameState>>,
     dice_data: Res<DiceData>,
     mut power_events: EventWriter<PowerEvent>,
+    game_data: Res<GameData>,
+    current_player_data: Res<CurrentPlayerData>,
+    settings: Res<GameSettings>,
+    mut undo_data: ResMut<UndoData>,
 ) {
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
     for action in action_events.iter() {
         if let Some((player, index)) = match action.0 {
             GameButtonAction::Done => {
-                if dice_data.dice.doubles {
+                if settings.rolls_again(&dice_data.dice, player_data) {
                     next_state.set(GameState::DiceRoll);
                 } else {
                     next_state.set(GameState::EndTurn);
                 }
                 None
             }
+            GameButtonAction::Undo => None, // handled by undo_move
+            GameButtonAction::Hint => None, // handled by show_hint
+            GameButtonAction::ChooseDie(_) => None, // handled by choose_die
             GameButtonAction::PowerUpOne(player) => Some((player, 0)),
             GameButtonAction::PowerUpTwo(player) => Some((player, 1)),
             GameButtonAction::PowerUpThree(player) => Some((player, 2)),
         } {
+            // power-ups can't be taken back so neither can the moves before them
+            undo_data.0 = None;
             power_events.send(PowerEvent::Use{ player, index });
         }
     }
 }
 
+/// Takes back the human player's last move, putting the marbles, dice, power
+/// bars and power-ups back the way they were.
+fn undo_move(
+    mut commands: Commands,
+    mut action_events: EventReader<ActionEvent<GameButtonAction>>,
+    mut undo_data: ResMut<UndoData>,
+    mut marbles: Query<(&mut Marble, &Transform, &Player)>,
+    mut power_bars: Query<(&mut PowerBar, &mut Transform, &Player), Without<Marble>>,
+    mut dice_sprites: Query<&mut TextureAtlasSprite>,
+    mut dice_data: ResMut<DiceData>,
+    mut game_data: ResMut<GameData>,
+    current_player_data: Res<CurrentPlayerData>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !action_events.iter().any(|action| matches!(action.0, GameButtonAction::Undo)) {
+        return;
+    }
+    let Some(undo) = undo_data.0.take() else {
+        return;
+    };
+
+    // send the moved marble back and return any captured marble to the board
+    for (entity, index, prev_index) in [Some((undo.marble, undo.index, undo.prev_index)), undo.captured].into_iter().flatten() {
+        let Ok((mut marble, transform, player)) = marbles.get_mut(entity) else {
+            continue;
+        };
+        marble.index = index;
+        marble.prev_index = prev_index;
+        let destination = if index == BOARD.len() {
+            marble.origin
+        } else {
+            let (x, y) = player.rotate_coords((BOARD[index].0 as f32, BOARD[index].1 as f32));
+            Vec3::new(x * TILE_SIZE, y * TILE_SIZE, Z_MARBLE)
+        };
+        commands.entity(entity).insert(Moving::new(destination, transform.translation));
+    }
+
+    // give back the dice
+    dice_data.dice = undo.dice;
+    for (die, unused) in [(dice_data.die_1, dice_data.dice.one.is_some()), (dice_data.die_2, dice_data.dice.two.is_some())] {
+        if unused {
+            commands.entity(die).remove::<UsedDie>();
+            if let Ok(mut sprite) = dice_sprites.get_mut(die) {
+                sprite.color = Color::WHITE;
+            }
+        }
+    }
+
+    // restore the power bars and remove any power-ups earned by the move
+    for (mut bar, mut transform, player) in &mut power_bars {
+        if let Some((_, power, count)) = undo.power_bars.iter().find(|(p, _, _)| p == player) {
+            bar.power = *power;
+            bar.power_up_count = *count;
+            bar.snap();
+            transform.translation.y = bar.fill_y();
+        }
+    }
+    for (player, power_ups) in undo.power_ups {
+        let player_data = game_data.players.get_mut(&player).unwrap();
+        for (_, e) in player_data.power_ups.iter().flatten() {
+            if !power_ups.iter().flatten().any(|(_, old)| old == e) {
+                commands.entity(*e).despawn();
+            }
+        }
+        player_data.power_ups = power_ups;
+    }
+    game_data.players.get_mut(&current_player_data.player).unwrap().power_up_status = undo.power_up_status;
+
+    highlight_events.send(HighlightEvent::Off);
+    next_state.set(GameState::TurnSetup);
+}
+
+/// Highlights the move the AI would make and explains why it would make it.
+fn show_hint(
+    mut action_events: EventReader<ActionEvent<GameButtonAction>>,
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>)>,
+    dice_data: Res<DiceData>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    if !action_events.iter().any(|action| matches!(action.0, GameButtonAction::Hint)) {
+        return;
+    }
+    let owner = current_player_data.marble_owner;
+    let board: Vec<MarbleState> = marbles.iter()
+        .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+        .collect();
+    let suggestion = suggest_move(owner, &board, &current_player_data.possible_moves, &settings);
+    let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    let power_up = helpful_power_up(owner, &board, &dice_data.dice, player_data, suggestion.as_ref(), &settings);
+
+    let mut hint = match suggestion {
+        Some(suggestion) => {
+            current_player_data.select_move((suggestion.entity, suggestion.marble_move));
+            highlight_events.send(HighlightEvent::On);
+            format!("This move {}", suggestion.reason)
+        }
+        None => "There are no moves - end your turn".to_string(),
+    };
+    if let Some(power_up) = power_up {
+        hint.push_str(&format!("\nYour {} power-up would help", power_up.name()));
+    }
+    notice_events.send(NoticeEvent(hint));
+}
+
+/// While a marble is selected, hovering over one of its destinations (or
+/// picking one with the keyboard) shows the path it would take, which dice the
+/// move would use, and the marble it would capture.
+fn preview_hovered_move(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    current_player_data: Res<CurrentPlayerData>,
+    marbles: Query<(&Marble, &Player, &Transform)>,
+    previews: Query<Entity, With<MovePreview>>,
+    dice_data: Res<DiceData>,
+    settings: Res<GameSettings>,
+    highlight_data: Res<HighlightData>,
+    asset_server: Res<AssetServer>,
+    mut hovered: Local<Option<(Entity, usize, Option<WhichDie>)>>,
+) {
+    let owner = current_player_data.marble_owner;
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let now_hovered = match (current_player_data.selected_marble, cursor) {
+        (Some(marble), Some(pos)) => {
+            let (col, row) = (snap(pos.x - WINDOW_SIZE / 2.0), snap(-(pos.y - WINDOW_SIZE / 2.0)));
+            BOARD.into_iter()
+                .position(|(x, y)| owner.rotate_coords((x as f32, y as f32)) == (col / TILE_SIZE, row / TILE_SIZE))
+                .filter(|index| current_player_data.get_moves(marble).iter().any(|m| m.destination == *index))
+                .map(|index| (marble, index, None))
+        }
+        _ => None,
+    }.or_else(|| current_player_data.get_selected_move()
+        .map(|(marble, m)| (marble, m.destination, Some(m.which)))
+    );
+    // (the preview is cleared whenever the turn ends, so redraw it if it's gone)
+    if now_hovered == *hovered && (hovered.is_none() || !previews.is_empty()) {
+        return;
+    }
+    *hovered = now_hovered;
+    previews.for_each(|e| commands.entity(e).despawn());
+
+    let Some((entity, destination, which)) = now_hovered else {
+        return;
+    };
+    let Ok((marble, _, _)) = marbles.get(entity) else {
+        return;
+    };
+    let moves: Vec<MarbleMove> = current_player_data.get_moves(entity).into_iter()
+        .filter(|m| m.destination == destination && which.is_none_or(|w| w == m.which))
+        .collect();
+    if moves.is_empty() {
+        return;
+    }
+    let tile_position = |index: usize, z: f32| {
+        let (x, y) = owner.rotate_coords((BOARD[index].0 as f32, BOARD[index].1 as f32));
+        Vec3::new(x * TILE_SIZE, y * TILE_SIZE, z)
+    };
+
+    // the path (the destination is already highlighted)
+    for index in move_path(marble.index, destination, moves[0].which).into_iter().filter(|i| *i != destination) {
+        commands.spawn((
+            SpriteBundle{
+                texture: highlight_data.tile_texture.clone(),
+                sprite: Sprite{ color: Color::rgba(1.0, 1.0, 1.0, 0.5), ..default() },
+                transform: Transform::from_translation(tile_position(index, Z_SELECTION_HIGHLIGHT))
+                    .with_scale(Vec3::splat(0.5)),
+                ..default()
+            },
+            MovePreview,
+        ));
+    }
+
+    // which dice the move uses
+    let label = moves.iter()
+        .map(|m| die_label(m.which, &dice_data.dice))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    commands.spawn((
+        Text2dBundle{
+            text: Text::from_section(
+                label.to_uppercase(),
+                TextStyle{
+                    font: asset_server.load("Kenney Mini.ttf"),
+                    font_size: 16.0,
+                    color: Color::WHITE,
+                },
+            ),
+            transform: Transform::from_translation(tile_position(destination, Z_UI) + Vec3::new(0.0, TILE_SIZE * 0.75, 0.0)),
+            ..default()
+        },
+        MovePreview,
+    ));
+
+    // the marble that would be captured
+    let can_capture = destination < FIRST_HOME_INDEX || destination == CENTER_INDEX;
+    if let Some((_, _, transform)) = marbles.iter()
+        .filter(|_| can_capture)
+        .filter(|(_, p, _)| **p != owner && !owner.is_teammate(**p, settings.team_play))
+        .filter(|(m, _, _)| m.index < FIRST_HOME_INDEX || m.index == CENTER_INDEX)
+        .find(|(m, p, _)| Player::is_same_index(owner, destination, **p, m.index))
+    {
+        commands.spawn((
+            SpriteBundle{
+                texture: highlight_data.marble_texture.clone(),
+                sprite: Sprite{ color: Color::rgb(1.0, 0.2, 0.2), ..default() },
+                transform: Transform::from_xyz(transform.translation.x, transform.translation.y, Z_SELECTION_HIGHLIGHT),
+                ..default()
+            },
+            MovePreview,
+        ));
+    }
+}
+
+/// Describes the dice used by a move, e.g. "die one (3)".
+fn die_label(which: WhichDie, dice: &Dice) -> String {
+    let value = |die: Option<u8>| die.unwrap_or(0) * dice.multiplier;
+    match which {
+        WhichDie::One => format!("die one ({})", value(dice.one)),
+        WhichDie::Two => format!("die two ({})", value(dice.two)),
+        WhichDie::Both => format!("both dice ({})", value(dice.one) + value(dice.two)),
+        WhichDie::Neither => "power-up".to_string(),
+    }
+}
+
+fn clear_move_preview(
+    mut commands: Commands,
+    previews: Query<Entity, With<MovePreview>>,
+) {
+    previews.for_each(|e| commands.entity(e).despawn());
+}
+
 /// Snaps the given coordinate to the center of the tile it's inside of.
 fn snap(coord: f32) -> f32 {
     // let's only deal with positive values for now
@@ -0,0 +1,417 @@
+use std::fs;
+use std::path::PathBuf;
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+
+/// Something the player wants to do, no matter which keys were used to do it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Event)]
+pub enum InputAction {
+    /// Focus the next marble (or button, or color).
+    Next,
+    /// Focus the previous marble (or button, or color).
+    Previous,
+    /// Cycle forward through the selected marble's destinations.
+    NextTarget,
+    /// Cycle backward through the selected marble's destinations.
+    PreviousTarget,
+    Confirm,
+    Cancel,
+    Done,
+    /// Use the power-up in the given slot.
+    PowerUp(usize),
+    Undo,
+    Hint,
+}
+
+impl InputAction {
+    /// Every action that can be bound to a key or gamepad button.
+    pub const ALL: [InputAction; 12] = [
+        InputAction::Next,
+        InputAction::Previous,
+        InputAction::NextTarget,
+        InputAction::PreviousTarget,
+        InputAction::Confirm,
+        InputAction::Cancel,
+        InputAction::Done,
+        InputAction::PowerUp(0),
+        InputAction::PowerUp(1),
+        InputAction::PowerUp(2),
+        InputAction::Undo,
+        InputAction::Hint,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            InputAction::Next => "next".to_string(),
+            InputAction::Previous => "previous".to_string(),
+            InputAction::NextTarget => "next move".to_string(),
+            InputAction::PreviousTarget => "previous move".to_string(),
+            InputAction::Confirm => "confirm".to_string(),
+            InputAction::Cancel => "cancel".to_string(),
+            InputAction::Done => "done".to_string(),
+            InputAction::PowerUp(slot) => format!("power-up {}", slot + 1),
+            InputAction::Undo => "undo".to_string(),
+            InputAction::Hint => "hint".to_string(),
+        }
+    }
+}
+
+/// A key or gamepad button that triggers an [`InputAction`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Pad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Pad(button) => format!("pad {:?}", button),
+        }
+    }
+}
+
+/// The keys that can be bound. Bindings are saved by name, and this is the
+/// list used to look those names back up.
+const BINDABLE_KEYS: [KeyCode; 79] = [
+    KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+    KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+    KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+    KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4,
+    KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::NumpadEnter, KeyCode::NumpadAdd, KeyCode::NumpadSubtract,
+    KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4, KeyCode::F5, KeyCode::F6,
+    KeyCode::F7, KeyCode::F8, KeyCode::F9, KeyCode::F10, KeyCode::F11, KeyCode::F12,
+    KeyCode::Left, KeyCode::Right, KeyCode::Up, KeyCode::Down,
+    KeyCode::Space, KeyCode::Return, KeyCode::Tab, KeyCode::Escape, KeyCode::Back,
+    KeyCode::Delete, KeyCode::Home, KeyCode::End, KeyCode::PageUp, KeyCode::PageDown,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Minus,
+];
+
+const BINDABLE_PAD_BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South, GamepadButtonType::East, GamepadButtonType::North, GamepadButtonType::West,
+    GamepadButtonType::C, GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger, GamepadButtonType::LeftTrigger2,
+    GamepadButtonType::RightTrigger, GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select, GamepadButtonType::Start, GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb, GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp, GamepadButtonType::DPadDown, GamepadButtonType::DPadLeft, GamepadButtonType::DPadRight,
+];
+
+const BINDABLE_MOUSE_BUTTONS: [MouseButton; 3] = [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+
+/// Which keys and gamepad buttons trigger which actions, along with the mouse
+/// button used to click. These are loaded from the user's config directory on
+/// startup and saved there whenever they change.
+#[derive(Clone, Debug, PartialEq, Resource)]
+pub struct Bindings {
+    pub actions: Vec<(InputAction, Vec<Binding>)>,
+    pub click: MouseButton,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::*;
+        Self {
+            actions: vec![
+                (InputAction::Next, vec![Key(KeyCode::Tab), Key(KeyCode::Right), Pad(GamepadButtonType::DPadRight)]),
+                (InputAction::Previous, vec![Key(KeyCode::Left), Pad(GamepadButtonType::DPadLeft)]),
+                (InputAction::NextTarget, vec![Key(KeyCode::Down), Pad(GamepadButtonType::DPadDown)]),
+                (InputAction::PreviousTarget, vec![Key(KeyCode::Up), Pad(GamepadButtonType::DPadUp)]),
+                (InputAction::Confirm, vec![Key(KeyCode::Return), Key(KeyCode::NumpadEnter), Pad(GamepadButtonType::South)]),
+                (InputAction::Cancel, vec![Key(KeyCode::Escape), Pad(GamepadButtonType::East)]),
+                (InputAction::Done, vec![Key(KeyCode::Space), Pad(GamepadButtonType::Start)]),
+                (InputAction::PowerUp(0), vec![Key(KeyCode::Key1), Pad(GamepadButtonType::LeftTrigger)]),
+                (InputAction::PowerUp(1), vec![Key(KeyCode::Key2), Pad(GamepadButtonType::RightTrigger)]),
+                (InputAction::PowerUp(2), vec![Key(KeyCode::Key3), Pad(GamepadButtonType::LeftTrigger2), Pad(GamepadButtonType::RightTrigger2)]),
+                (InputAction::Undo, vec![Key(KeyCode::U), Pad(GamepadButtonType::West)]),
+                (InputAction::Hint, vec![Key(KeyCode::H), Pad(GamepadButtonType::North)]),
+            ],
+            click: MouseButton::Left,
+        }
+    }
+}
+
+impl Bindings {
+    /// The action bound to a key or gamepad button, if there is one.
+    pub fn action_for(&self, binding: Binding) -> Option<InputAction> {
+        self.actions.iter()
+            .find(|(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    /// The action for a key press. Shift+Tab goes backward when Tab is bound to
+    /// next, like it does everywhere else.
+    pub fn action_for_key(&self, key: KeyCode, shift: bool) -> Option<InputAction> {
+        match self.action_for(Binding::Key(key)) {
+            Some(InputAction::Next) if shift && key == KeyCode::Tab => Some(InputAction::Previous),
+            action => action,
+        }
+    }
+
+    pub fn bindings_for(&self, action: InputAction) -> &[Binding] {
+        self.actions.iter()
+            .find(|(a, _)| *a == action)
+            .map_or(&[], |(_, bindings)| bindings.as_slice())
+    }
+
+    /// Binds `binding` to `action`. It replaces the action's other bindings for
+    /// the same device and is taken away from any other action using it.
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) {
+        let same_device = |b: &Binding| matches!((b, binding), (Binding::Key(_), Binding::Key(_)) | (Binding::Pad(_), Binding::Pad(_)));
+        for (a, bindings) in self.actions.iter_mut() {
+            if *a == action {
+                bindings.retain(|b| !same_device(b));
+                bindings.push(binding);
+            } else {
+                bindings.retain(|b| *b != binding);
+            }
+        }
+    }
+
+    /// Writes the bindings as lines of `action = binding, binding`.
+    pub fn to_text(&self) -> String {
+        let mut text = "# Vexation controls - delete this file to go back to the defaults\n".to_string();
+        text.push_str(&format!("Click = {:?}\n", self.click));
+        for (action, bindings) in &self.actions {
+            let bindings: Vec<String> = bindings.iter().map(|b| format!("{:?}", b)).collect();
+            text.push_str(&format!("{:?} = {}\n", action, bindings.join(", ")));
+        }
+        text
+    }
+
+    /// Reads bindings written by [`Bindings::to_text`]. Anything missing or
+    /// unrecognized keeps its default binding.
+    pub fn from_text(text: &str) -> Self {
+        let mut bindings = Bindings::default();
+        let candidates: Vec<Binding> = BINDABLE_KEYS.iter().map(|k| Binding::Key(*k))
+            .chain(BINDABLE_PAD_BUTTONS.iter().map(|b| Binding::Pad(*b)))
+            .collect();
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#')) {
+            let Some((name, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (name, value) = (name.trim(), value.trim());
+            if name == "Click" {
+                if let Some(button) = BINDABLE_MOUSE_BUTTONS.iter().find(|b| format!("{:?}", b) == value) {
+                    bindings.click = *button;
+                }
+                continue;
+            }
+            let Some(action) = InputAction::ALL.iter().find(|a| format!("{:?}", a) == name) else {
+                continue;
+            };
+            let parsed: Vec<Binding> = value.split(',')
+                .filter_map(|v| candidates.iter().find(|c| format!("{:?}", c) == v.trim()).copied())
+                .collect();
+            if parsed.is_empty() {
+                continue;
+            }
+            if let Some((_, action_bindings)) = bindings.actions.iter_mut().find(|(a, _)| a == action) {
+                *action_bindings = parsed;
+            }
+        }
+        bindings
+    }
+
+    pub fn load() -> Self {
+        config_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map_or_else(Bindings::default, |text| Bindings::from_text(&text))
+    }
+
+    pub fn save(&self) {
+        let Some(path) = config_path() else {
+            return;
+        };
+        let result = path.parent().map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, self.to_text()));
+        if let Err(e) = result {
+            eprintln!("unable to save controls to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Where the bindings are saved - `vexation/controls.txt` under the user's
+/// config directory.
+fn config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| {
+            let home = PathBuf::from(home);
+            if cfg!(target_os = "macos") {
+                home.join("Library").join("Application Support")
+            } else {
+                home.join(".config")
+            }
+        }))?;
+    Some(config_dir.join("vexation").join("controls.txt"))
+}
+
+/// The action waiting for the player to press the key or button it should be
+/// bound to. While waiting, input isn't turned into actions.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Resource)]
+pub enum Rebinding {
+    #[default]
+    Idle,
+    Action(InputAction),
+    Click,
+}
+
+/// Turns raw keyboard and gamepad input into [`InputAction`]s. These are sent
+/// before the `Update` schedule runs so every system sees them the frame they
+/// happen.
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<InputAction>()
+            .insert_resource(Bindings::load())
+            .init_resource::<Rebinding>()
+            .add_systems(PreUpdate, (capture_binding, keyboard_input, gamepad_input).chain().after(InputSystem))
+            ;
+    }
+}
+
+/// Binds the next key, gamepad button, or mouse button pressed to the action
+/// waiting for one.
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut bindings: ResMut<Bindings>,
+    keys: Res<Input<KeyCode>>,
+    pad_buttons: Res<Input<GamepadButton>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+) {
+    let captured = match *rebinding {
+        Rebinding::Idle => return,
+        Rebinding::Action(action) => keys.get_just_pressed()
+            .find(|k| BINDABLE_KEYS.contains(k))
+            .map(|k| Binding::Key(*k))
+            .or_else(|| pad_buttons.get_just_pressed()
+                .find(|b| BINDABLE_PAD_BUTTONS.contains(&b.button_type))
+                .map(|b| Binding::Pad(b.button_type))
+            )
+            .map(|binding| bindings.rebind(action, binding))
+            .is_some(),
+        Rebinding::Click => BINDABLE_MOUSE_BUTTONS.iter()
+            .find(|b| mouse_buttons.just_pressed(**b))
+            .map(|b| bindings.click = *b)
+            .is_some(),
+    };
+    if captured {
+        bindings.save();
+        *rebinding = Rebinding::Idle;
+    }
+}
+
+fn keyboard_input(
+    keys: Res<Input<KeyCode>>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    mut input_actions: EventWriter<InputAction>,
+) {
+    // the key that was just bound shouldn't also do something
+    if *rebinding != Rebinding::Idle || rebinding.is_changed() {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for key in keys.get_just_pressed() {
+        if let Some(action) = bindings.action_for_key(*key, shift) {
+            input_actions.send(action);
+        }
+    }
+}
+
+/// How far the stick has to be pushed before it counts as a press.
+const STICK_THRESHOLD: f32 = 0.5;
+
+fn gamepad_input(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    mut stick_pushed: Local<[bool; 4]>,
+    mut input_actions: EventWriter<InputAction>,
+) {
+    // the button that was just bound shouldn't also do something
+    if *rebinding != Rebinding::Idle || rebinding.is_changed() {
+        return;
+    }
+    let mut pushed = [false; 4];
+    for gamepad in gamepads.iter() {
+        for button in buttons.get_just_pressed().filter(|b| b.gamepad == gamepad) {
+            if let Some(action) = bindings.action_for(Binding::Pad(button.button_type)) {
+                input_actions.send(action);
+            }
+        }
+
+        // the left stick works like the d-pad
+        let x = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+        pushed[0] |= x > STICK_THRESHOLD;
+        pushed[1] |= x < -STICK_THRESHOLD;
+        pushed[2] |= y < -STICK_THRESHOLD;
+        pushed[3] |= y > STICK_THRESHOLD;
+    }
+    // only send an action when the stick is first pushed in a direction
+    let stick_actions = [InputAction::Next, InputAction::Previous, InputAction::NextTarget, InputAction::PreviousTarget];
+    for (i, action) in stick_actions.into_iter().enumerate() {
+        if pushed[i] && !stick_pushed[i] {
+            input_actions.send(action);
+        }
+    }
+    *stick_pushed = pushed;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bindings_text_round_trip() {
+        let mut bindings = Bindings::default();
+        bindings.rebind(InputAction::Done, Binding::Key(KeyCode::D));
+        bindings.rebind(InputAction::Hint, Binding::Pad(GamepadButtonType::Select));
+        bindings.click = MouseButton::Right;
+        assert_eq!(bindings, Bindings::from_text(&bindings.to_text()));
+    }
+
+    #[test]
+    fn test_rebind() {
+        let mut bindings = Bindings::default();
+        // space moves from done to confirm, replacing confirm's other keys
+        bindings.rebind(InputAction::Confirm, Binding::Key(KeyCode::Space));
+        assert_eq!(Some(InputAction::Confirm), bindings.action_for(Binding::Key(KeyCode::Space)));
+        assert_eq!(None, bindings.action_for(Binding::Key(KeyCode::Return)));
+        assert_eq!(&[Binding::Pad(GamepadButtonType::Start)], bindings.bindings_for(InputAction::Done));
+        // gamepad bindings are left alone
+        assert_eq!(Some(InputAction::Confirm), bindings.action_for(Binding::Pad(GamepadButtonType::South)));
+    }
+
+    #[test]
+    fn test_shift_tab() {
+        let mut bindings = Bindings::default();
+        assert_eq!(Some(InputAction::Next), bindings.action_for_key(KeyCode::Tab, false));
+        assert_eq!(Some(InputAction::Previous), bindings.action_for_key(KeyCode::Tab, true));
+        assert_eq!(Some(InputAction::Next), bindings.action_for_key(KeyCode::Right, true));
+        // once tab is bound to something else shift doesn't change it
+        bindings.rebind(InputAction::Done, Binding::Key(KeyCode::Tab));
+        assert_eq!(Some(InputAction::Done), bindings.action_for_key(KeyCode::Tab, true));
+    }
+
+    #[test]
+    fn test_bindings_from_bad_text() {
+        let bindings = Bindings::from_text("Done = Key(Nope)\nNonsense\nClick = Sideways\nHint = Key(Nope), Key(J)");
+        assert_eq!(Bindings::default().bindings_for(InputAction::Done), bindings.bindings_for(InputAction::Done));
+        assert_eq!(&[Binding::Key(KeyCode::J)], bindings.bindings_for(InputAction::Hint));
+        assert_eq!(MouseButton::Left, bindings.click);
+        assert_eq!(Bindings::default().bindings_for(InputAction::Next), bindings.bindings_for(InputAction::Next));
+    }
+}
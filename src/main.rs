@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 
+mod ai;
 mod buttons;
 mod components;
 mod computer_turn;
@@ -8,16 +9,22 @@ mod choose_color;
 mod dice_roll;
 mod end_turn;
 mod human_turn;
+mod input;
 mod main_menu;
 mod next_player;
+mod notice;
 mod power;
+mod power_hud;
 mod process;
 mod resources;
 mod shared_systems;
+mod targeting;
+mod threat_overlay;
 mod turn_setup;
 mod vexation;
 
 use constants::*;
+use input::InputPlugin;
 use main_menu::*;
 use vexation::VexationPlugin;
 
@@ -38,6 +45,7 @@ fn main() {
             }),
             ..default()
         }).set(ImagePlugin::default_nearest()))
+        .add_plugins(InputPlugin)
         .add_plugins(MainMenuPlugin)
         .add_plugins(VexationPlugin)
 
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
 use bevy::app::AppExit;
+use bevy::sprite::Anchor;
+use bevy::text::Text2dBounds;
 use bevy::window::PrimaryWindow;
 use crate::buttons::*;
 use crate::constants::*;
+use crate::input::{Bindings, InputAction, Rebinding};
+use crate::power::PowerUp;
 use crate::resources::*;
 
 #[derive(Clone, Copy)]
@@ -10,9 +14,102 @@ enum MainMenuAction {
     StartGame,
     NextPage,
     PrevPage,
+    Settings,
+    MainPage,
+    Change(SettingsItem),
+    Controls,
+    Rebind(Rebinding),
     Quit,
 }
 
+/// The settings that can be changed from the settings page.
+#[derive(Clone, Copy)]
+enum SettingsItem {
+    TeamPlay,
+    MarbleCount,
+    UseBothDice,
+    Blockade,
+    Doubles,
+    ThreatOverlay,
+    AutoForcedMove,
+    AutoPass,
+    DraftPowerUps,
+}
+
+impl SettingsItem {
+    const ALL: [SettingsItem; 9] = [
+        SettingsItem::TeamPlay,
+        SettingsItem::MarbleCount,
+        SettingsItem::UseBothDice,
+        SettingsItem::Blockade,
+        SettingsItem::Doubles,
+        SettingsItem::ThreatOverlay,
+        SettingsItem::AutoForcedMove,
+        SettingsItem::AutoPass,
+        SettingsItem::DraftPowerUps,
+    ];
+
+    fn label(&self, settings: &GameSettings) -> String {
+        let on_off = |on: bool| if on { "ON" } else { "OFF" };
+        match self {
+            SettingsItem::TeamPlay => format!("TEAM PLAY: {}", on_off(settings.team_play)),
+            SettingsItem::MarbleCount => format!("MARBLES: {}", settings.marble_count),
+            SettingsItem::UseBothDice => format!("MUST USE BOTH DICE: {}", on_off(settings.use_both_dice)),
+            SettingsItem::Blockade => match settings.blockade {
+                Some(size) => format!("BLOCKADES: {} IN A ROW", size),
+                None => "BLOCKADES: OFF".to_string(),
+            },
+            SettingsItem::Doubles => match settings.doubles {
+                DoublesRule::RollAgain => "DOUBLES: ROLL AGAIN",
+                DoublesRule::ThreeStrikes => "DOUBLES: 3RD SENDS LEAD HOME",
+                DoublesRule::OneExtraRoll => "DOUBLES: ONE EXTRA ROLL",
+                DoublesRule::NoReroll => "DOUBLES: NO REROLL",
+            }.to_string(),
+            SettingsItem::ThreatOverlay => format!("THREAT OVERLAY: {}", on_off(settings.threat_overlay)),
+            SettingsItem::AutoForcedMove => format!("AUTO-PLAY FORCED MOVES: {}", on_off(settings.auto_forced_move)),
+            SettingsItem::AutoPass => format!("AUTO-PASS WITH NO MOVES: {}", on_off(settings.auto_pass)),
+            SettingsItem::DraftPowerUps => format!("POWER-UP DRAFT: {}", on_off(settings.draft_power_ups)),
+        }
+    }
+
+    fn change(&self, settings: &mut GameSettings) {
+        match self {
+            SettingsItem::TeamPlay => settings.team_play = !settings.team_play,
+            SettingsItem::UseBothDice => settings.use_both_dice = !settings.use_both_dice,
+            SettingsItem::ThreatOverlay => settings.threat_overlay = !settings.threat_overlay,
+            SettingsItem::AutoForcedMove => settings.auto_forced_move = !settings.auto_forced_move,
+            SettingsItem::AutoPass => settings.auto_pass = !settings.auto_pass,
+            SettingsItem::DraftPowerUps => settings.draft_power_ups = !settings.draft_power_ups,
+            SettingsItem::Blockade => {
+                settings.blockade = match settings.blockade {
+                    None => Some(2),
+                    Some(2) => Some(3),
+                    _ => None,
+                };
+            }
+            SettingsItem::Doubles => {
+                settings.doubles = match settings.doubles {
+                    DoublesRule::RollAgain => DoublesRule::ThreeStrikes,
+                    DoublesRule::ThreeStrikes => DoublesRule::OneExtraRoll,
+                    DoublesRule::OneExtraRoll => DoublesRule::NoReroll,
+                    DoublesRule::NoReroll => DoublesRule::RollAgain,
+                };
+            }
+            SettingsItem::MarbleCount => {
+                settings.marble_count = if settings.marble_count >= MAX_MARBLES {
+                    MIN_MARBLES
+                } else {
+                    settings.marble_count + 1
+                };
+            }
+        }
+    }
+}
+
+/// The settings pages are kept well out of the way of the rules pages.
+const SETTINGS_PAGE: usize = usize::MAX;
+const CONTROLS_PAGE: usize = usize::MAX - 1;
+
 #[derive(Resource)]
 struct RootUiEntities{
     pub ui: Vec<Entity>,
@@ -28,6 +125,9 @@ struct UiAssets {
     pub quit_button: Handle<TextureAtlas>,
     pub back_button: Handle<TextureAtlas>,
     pub next_button: Handle<TextureAtlas>,
+    pub power_fill: Handle<Image>,
+    /// One sprite sheet per power-up, in the same order as `PowerUp::from`.
+    pub power_ups: Vec<Handle<TextureAtlas>>,
 }
 
 #[derive(Resource)]
@@ -42,6 +142,7 @@ impl Plugin for MainMenuPlugin {
             .add_event::<ActionEvent<MainMenuAction>>()
 
             .insert_resource(UiPageNumber(0))
+            .init_resource::<GameSettings>()
 
             .add_systems(Startup, setup)
 
@@ -49,9 +150,12 @@ impl Plugin for MainMenuPlugin {
             // I'm executing button actions first because I want a frame
             // delay here so we can see the button animation
             .add_systems(Update, (
+                    back_to_main_page,
                     execute_menu_action,
                     mouse_watcher::<MainMenuAction>,
+                    focus_watcher::<MainMenuAction>,
                     watch_button_state_changes,
+                    watch_text_button_state_changes,
                     menu_page_renderer
                 ).chain()
                 .run_if(in_state(GameState::MainMenu))
@@ -76,6 +180,10 @@ fn setup(
         quit_button: load_sprite_sheet("buttons/quit_button.png", UI_BUTTON_SIZE.clone(), grid, &asset_server, &mut texture_atlases),
         back_button: load_sprite_sheet("buttons/back_button.png", UI_BUTTON_SIZE.clone(), grid, &asset_server, &mut texture_atlases),
         next_button: load_sprite_sheet("buttons/next_button.png", UI_BUTTON_SIZE.clone(), grid, &asset_server, &mut texture_atlases),
+        power_fill: asset_server.load("power-fill.png"),
+        power_ups: ["roll-again", "double-dice", "evade-capture", "self-jump", "capture-nearest", "home-run", "swap", "freeze", "shield"].iter()
+            .map(|name| load_sprite_sheet(&format!("power-ups/{}-sheet.png", name), TILE_BUTTON_SIZE, grid, &asset_server, &mut texture_atlases))
+            .collect(),
     });
 }
 
@@ -84,11 +192,12 @@ fn main_menu_enter(
     ui_assets: Res<UiAssets>,
     windows: Query<&Window, With<PrimaryWindow>>,
     mouse_button_input: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
 ) {
     let Ok(w) = windows.get_single() else {
         return;
     };
-    let mouse_pressed = mouse_button_input.pressed(MouseButton::Left);
+    let mouse_pressed = mouse_button_input.pressed(bindings.click);
     let ui = create_main_menu(&mut commands, &ui_assets, w.cursor_position(), mouse_pressed);
     commands.insert_resource(RootUiEntities{ ui });
 }
@@ -108,17 +217,35 @@ fn execute_menu_action(
     mut next_state: ResMut<NextState<GameState>>,
     mut page_number: ResMut<UiPageNumber>,
     mut app_exit_events: EventWriter<AppExit>,
+    mut settings: ResMut<GameSettings>,
+    mut rebinding: ResMut<Rebinding>,
 ) {
     for action in action_events.iter() {
         match action.0 {
             MainMenuAction::StartGame => next_state.set(GameState::GameStart),
             MainMenuAction::NextPage => page_number.0 += 1,
             MainMenuAction::PrevPage => page_number.0 -= 1,
+            MainMenuAction::Settings => page_number.0 = SETTINGS_PAGE,
+            MainMenuAction::MainPage => page_number.0 = 0,
+            MainMenuAction::Change(item) => item.change(&mut settings),
+            MainMenuAction::Controls => page_number.0 = CONTROLS_PAGE,
+            MainMenuAction::Rebind(r) => *rebinding = r,
             MainMenuAction::Quit => app_exit_events.send(AppExit),
         }
     }
 }
 
+/// Cancelling from any other page goes back to the main page (or to the
+/// settings page from the controls page).
+fn back_to_main_page(
+    mut input_actions: EventReader<InputAction>,
+    mut page_number: ResMut<UiPageNumber>,
+) {
+    if input_actions.iter().any(|a| *a == InputAction::Cancel) && page_number.0 != 0 {
+        page_number.0 = if page_number.0 == CONTROLS_PAGE { SETTINGS_PAGE } else { 0 };
+    }
+}
+
 /// Renders the current page in the menu if a page change occurred.
 fn menu_page_renderer(
     page_number: Res<UiPageNumber>,
@@ -128,10 +255,16 @@ fn menu_page_renderer(
     ui_assets: Res<UiAssets>,
     windows: Query<&Window, With<PrimaryWindow>>,
     mouse_button_input: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    rebinding: Res<Rebinding>,
+    settings: Res<GameSettings>,
 ) {
     // check to see if we event need to render anything
     let render_page = match *current_page_number {
-        Some(p) if page_number.0 != p => {
+        // the settings pages are re-rendered when a setting changes so their labels are up to date
+        Some(p) if page_number.0 != p ||
+                   (p == SETTINGS_PAGE && settings.is_changed()) ||
+                   (p == CONTROLS_PAGE && (bindings.is_changed() || rebinding.is_changed())) => {
             // destroy the current page so the next one can be rendered
             for entity in &root_entities.ui {
                 commands.entity(*entity).despawn_recursive();
@@ -148,12 +281,15 @@ fn menu_page_renderer(
     let Ok(w) = windows.get_single() else {
         return;
     };
-    let mouse_pressed = mouse_button_input.pressed(MouseButton::Left);
+    let mouse_pressed = mouse_button_input.pressed(bindings.click);
     if let Some(p) = render_page {
         *current_page_number = render_page;
         let ui = match p {
             0 => create_main_menu(&mut commands, &ui_assets, w.cursor_position(), mouse_pressed),
-            1 | 2 | 3 => create_rules_page(&mut commands, ui_assets, page_number, w.cursor_position(), mouse_pressed),
+            1..=4 => create_rules_page(&mut commands, ui_assets, page_number, w.cursor_position(), mouse_pressed),
+            POWER_UPS_PAGE => create_power_ups_page(&mut commands, &ui_assets, w.cursor_position(), mouse_pressed),
+            SETTINGS_PAGE => create_settings_page(&mut commands, &ui_assets, &settings, w.cursor_position(), mouse_pressed),
+            CONTROLS_PAGE => create_controls_page(&mut commands, &ui_assets, &bindings, *rebinding, w.cursor_position(), mouse_pressed),
             _ => unreachable!(),
         };
         root_entities.ui = ui;
@@ -203,6 +339,16 @@ fn create_main_menu(
                 ButtonSize(UI_BUTTON_SIZE.clone()),
             );
 
+            transform.translation -= Vec3::new(0.0, y_offset, 0.0);
+            parent.spawn(text_button_bundle(
+                "SETTINGS",
+                ui_assets.mini_font.clone(),
+                transform,
+                ButtonAction(ActionEvent(MainMenuAction::Settings)),
+                get_button_state(cursor_pos, transform.translation, UI_BUTTON_SIZE, mouse_pressed),
+                ButtonSize(UI_BUTTON_SIZE),
+            ));
+
             transform.translation -= Vec3::new(0.0, y_offset, 0.0);
             spawn_sprite_sheet_button(
                 parent,
@@ -257,7 +403,17 @@ A marble can only exit with a die roll of 1 but can then optionally use the othe
 
 A marble can only exit to the corner with the same colored arrow.
 "#;
-// TOOD: POWER-UPS
+const RULES_P4: &str =
+r#"- Power Bar -
+
+Moving fills your power bar - a full lap (48 tiles) fills it once and HOME row moves count double. Capturing adds 3 power, being captured takes 3 away.
+
+A full bar earns a random power-up (or a pick of 3 with POWER-UP DRAFT on).
+
+With all 3 slots taken the bar holds its power-up until one opens - discard one to make room. Drag power-ups to reorder them.
+"#;
+/// The last rules page lists every power-up next to its icon.
+const POWER_UPS_PAGE: usize = 5;
 
 fn create_rules_page(
     commands: &mut Commands,
@@ -273,6 +429,7 @@ fn create_rules_page(
                     1 => RULES_P1,
                     2 => RULES_P2,
                     3 => RULES_P3,
+                    4 => RULES_P4,
                     _ => unreachable!(),
                 },
                 TextStyle{
@@ -292,13 +449,116 @@ fn create_rules_page(
         })
         .id()
         ;
+    let mut entities = vec![text];
+
+    if page_number.0 == 4 {
+        // a full power bar turning into power-ups in the 3 slots
+        entities.push(commands
+            .spawn(SpatialBundle::from_transform(Transform::from_xyz(0.0, -(WINDOW_SIZE / 2.0) + TILE_SIZE * 2.75, 1.0)))
+            .with_children(|parent| {
+                parent.spawn(SpriteBundle{
+                    texture: ui_assets.power_fill.clone(),
+                    // the power fill stands up on the board but lies down here
+                    transform: Transform::from_xyz(-TILE_SIZE * 3.0, 0.0, 0.0)
+                        .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)),
+                    ..default()
+                });
+                parent.spawn(Text2dBundle{
+                    text: Text::from_section(
+                        "=",
+                        TextStyle{
+                            font: ui_assets.mini_font.clone(),
+                            font_size: 24.0,
+                            color: Color::WHITE,
+                        }
+                    ),
+                    transform: Transform::from_xyz(TILE_SIZE * 0.75, 0.0, 0.0),
+                    ..default()
+                });
+                for (i, power_up) in ui_assets.power_ups.iter().take(3).enumerate() {
+                    parent.spawn(SpriteSheetBundle{
+                        texture_atlas: power_up.clone(),
+                        transform: Transform::from_xyz(TILE_SIZE * (2.0 + i as f32 * 1.25), 0.0, 0.0),
+                        ..default()
+                    });
+                }
+            })
+            .id()
+        );
+    }
+
+    entities.push(spawn_rules_buttons(commands, &ui_assets, page_number.0, cursor_pos, mouse_pressed));
+    entities
+}
+
+fn create_power_ups_page(
+    commands: &mut Commands,
+    ui_assets: &Res<UiAssets>,
+    cursor_pos: Option<Vec2>,
+    mouse_pressed: bool,
+) -> Vec<Entity> {
+    let root = commands
+        .spawn(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle{
+                text: Text::from_section(
+                    "- Power-Ups -",
+                    TextStyle{
+                        font: ui_assets.mini_font.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    }
+                ),
+                transform: Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE, 1.0),
+                ..default()
+            });
+
+            // one row per power-up - its icon, then what it does and for how long
+            // (the rows are packed in so the last one clears the back button)
+            const ROW_HEIGHT: f32 = TILE_SIZE * 1.4;
+            let text_width = WINDOW_SIZE - TILE_SIZE * 3.0;
+            for (i, sprite_sheet) in ui_assets.power_ups.iter().enumerate() {
+                let power_up = PowerUp::from(i);
+                let y = WINDOW_SIZE / 2.0 - TILE_SIZE * 2.5 - ROW_HEIGHT * i as f32;
+                parent.spawn(SpriteSheetBundle{
+                    texture_atlas: sprite_sheet.clone(),
+                    transform: Transform::from_xyz(-WINDOW_SIZE / 2.0 + TILE_SIZE, y, 1.0),
+                    ..default()
+                });
+                let style = |color: Color| TextStyle{ font: ui_assets.mini_font.clone(), font_size: 14.0, color };
+                parent.spawn(Text2dBundle{
+                    text: Text::from_sections([
+                        TextSection::new(format!("{} - ", power_up.name().to_uppercase()), style(Color::rgb(1.0, 0.85, 0.2))),
+                        TextSection::new(format!("{} Lasts {}.", power_up.description(), power_up.duration()), style(Color::WHITE)),
+                    ]),
+                    text_anchor: Anchor::CenterLeft,
+                    text_2d_bounds: Text2dBounds{ size: Vec2::new(text_width, ROW_HEIGHT) },
+                    transform: Transform::from_xyz(-WINDOW_SIZE / 2.0 + TILE_SIZE * 2.0, y, 1.0),
+                    ..default()
+                });
+            }
+        })
+        .id()
+        ;
+
+    vec![root, spawn_rules_buttons(commands, ui_assets, POWER_UPS_PAGE, cursor_pos, mouse_pressed)]
+}
 
-    let buttons = commands
+/// The back button (and the next button on all but the last page) along the
+/// bottom of a rules page.
+fn spawn_rules_buttons(
+    commands: &mut Commands,
+    ui_assets: &Res<UiAssets>,
+    page_number: usize,
+    cursor_pos: Option<Vec2>,
+    mouse_pressed: bool,
+) -> Entity {
+    commands
         .spawn(SpatialBundle::default())
         .with_children(|parent| {
             const BOTTOM_BUTTON_Y: f32 = (-WINDOW_SIZE / 2.0) + TILE_SIZE;
-            let x_offset = match page_number.0 {
-                1 | 2 => {
+            let x_offset = match page_number {
+                p if p < POWER_UPS_PAGE => {
                     let x_offset = (160.0 / 2.0) + 20.0;
                     let transform = Transform::from_xyz(x_offset, BOTTOM_BUTTON_Y, 5.0);
                     spawn_sprite_sheet_button(
@@ -326,7 +586,143 @@ fn create_rules_page(
             );
         })
         .id()
+}
+
+fn create_settings_page(
+    commands: &mut Commands,
+    ui_assets: &Res<UiAssets>,
+    settings: &GameSettings,
+    cursor_pos: Option<Vec2>,
+    mouse_pressed: bool,
+) -> Vec<Entity> {
+    let root = commands
+        .spawn(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle{
+                text: Text::from_section(
+                    "- Settings -",
+                    TextStyle{
+                        font: ui_assets.mini_font.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    }
+                ),
+                transform: Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE, 1.0),
+                ..default()
+            });
+
+            // one row per setting - clicking a row changes that setting
+            let row_size = Vec2::new(WINDOW_SIZE - TILE_SIZE * 2.0, TILE_SIZE);
+            for (i, item) in SettingsItem::ALL.iter().enumerate() {
+                let transform = Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE * (3.0 + i as f32 * 1.25), 1.0);
+                parent.spawn(text_button_bundle(
+                    item.label(settings),
+                    ui_assets.mini_font.clone(),
+                    transform,
+                    ButtonAction(ActionEvent(MainMenuAction::Change(*item))),
+                    get_button_state(cursor_pos, transform.translation, row_size, mouse_pressed),
+                    ButtonSize(row_size),
+                ));
+            }
+
+            let transform = Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE * (3.0 + SettingsItem::ALL.len() as f32 * 1.25), 1.0);
+            parent.spawn(text_button_bundle(
+                "CONTROLS",
+                ui_assets.mini_font.clone(),
+                transform,
+                ButtonAction(ActionEvent(MainMenuAction::Controls)),
+                get_button_state(cursor_pos, transform.translation, row_size, mouse_pressed),
+                ButtonSize(row_size),
+            ));
+
+            let transform = Transform::from_xyz(0.0, (-WINDOW_SIZE / 2.0) + TILE_SIZE, 5.0);
+            spawn_sprite_sheet_button(
+                parent,
+                ui_assets.back_button.clone(),
+                transform,
+                ButtonAction(ActionEvent(MainMenuAction::MainPage)),
+                Visibility::Inherited,
+                get_button_state(cursor_pos, transform.translation, UI_BUTTON_SIZE, mouse_pressed),
+                ButtonSize(UI_BUTTON_SIZE),
+            );
+        })
+        .id()
         ;
 
-    vec![text, buttons]
+    vec![root]
+}
+
+fn create_controls_page(
+    commands: &mut Commands,
+    ui_assets: &Res<UiAssets>,
+    bindings: &Bindings,
+    rebinding: Rebinding,
+    cursor_pos: Option<Vec2>,
+    mouse_pressed: bool,
+) -> Vec<Entity> {
+    let root = commands
+        .spawn(SpatialBundle::default())
+        .with_children(|parent| {
+            parent.spawn(Text2dBundle{
+                text: Text::from_section(
+                    "- Controls -",
+                    TextStyle{
+                        font: ui_assets.mini_font.clone(),
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                    }
+                ),
+                transform: Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE, 1.0),
+                ..default()
+            });
+
+            // one row per action - clicking a row waits for the key or button to bind to it
+            let mut rows = vec![(
+                Rebinding::Click,
+                if rebinding == Rebinding::Click {
+                    "CLICK: PRESS A MOUSE BUTTON".to_string()
+                } else {
+                    format!("CLICK: MOUSE {:?}", bindings.click)
+                },
+            )];
+            rows.extend(InputAction::ALL.iter().map(|action| {
+                let keys = if rebinding == Rebinding::Action(*action) {
+                    "PRESS A KEY OR BUTTON".to_string()
+                } else {
+                    let keys: Vec<String> = bindings.bindings_for(*action).iter().map(|b| b.name()).collect();
+                    if keys.is_empty() { "NONE".to_string() } else { keys.join(", ") }
+                };
+                (Rebinding::Action(*action), format!("{}: {}", action.name(), keys).to_uppercase())
+            }));
+
+            let row_height = 28.0;
+            let row_size = Vec2::new(WINDOW_SIZE - TILE_SIZE * 2.0, row_height);
+            for (i, (rebind, label)) in rows.into_iter().enumerate() {
+                let transform = Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE * 2.5 - i as f32 * row_height, 1.0)
+                    .with_scale(Vec3::splat(0.75));
+                parent.spawn(text_button_bundle(
+                    label,
+                    ui_assets.mini_font.clone(),
+                    transform,
+                    ButtonAction(ActionEvent(MainMenuAction::Rebind(rebind))),
+                    get_button_state(cursor_pos, transform.translation, row_size, mouse_pressed),
+                    ButtonSize(row_size),
+                ));
+            }
+
+            let transform = Transform::from_xyz(0.0, (-WINDOW_SIZE / 2.0) + TILE_SIZE, 5.0);
+            spawn_sprite_sheet_button(
+                parent,
+                ui_assets.back_button.clone(),
+                transform,
+                ButtonAction(ActionEvent(MainMenuAction::Settings)),
+                Visibility::Inherited,
+                get_button_state(cursor_pos, transform.translation, UI_BUTTON_SIZE, mouse_pressed),
+                ButtonSize(UI_BUTTON_SIZE),
+            );
+        })
+        .id()
+        ;
+
+    vec![root]
 }
@@ -7,7 +7,8 @@ use crate::resources::*;
 pub fn choose_next_player(
     mut commands: Commands,
     mut current_player_data: ResMut<CurrentPlayerData>,
-    marbles: Query<(Entity, &Player, Option<&CurrentPlayer>), With<Marble>>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&CurrentPlayer>)>,
+    settings: Res<GameSettings>,
 ) {
     // move clockwise to the next player
     current_player_data.player = match current_player_data.player {
@@ -17,30 +18,42 @@ pub fn choose_next_player(
         Player::Yellow => Player::Red,
     };
 
+    // in team play, a player with all of their marbles home moves their partner's marbles instead
+    let all_home = marbles.iter()
+        .filter(|(_, _, color, _)| **color == current_player_data.player)
+        .all(|(_, m, _, _)| (FIRST_HOME_INDEX..=settings.last_home_index()).contains(&m.index));
+    current_player_data.marble_owner = if settings.team_play && all_home {
+        current_player_data.player.partner()
+    } else {
+        current_player_data.player
+    };
+
     // update the marbles accordingly
-    for (marble, color, current_player) in &marbles {
+    for (marble, _, color, current_player) in &marbles {
         if current_player.is_some() {
             commands.entity(marble).remove::<CurrentPlayer>();
         }
-        if *color == current_player_data.player {
+        if *color == current_player_data.marble_owner {
             commands.entity(marble).insert(CurrentPlayer);
         }
     }
 }
 
 pub fn show_or_hide_buttons(
-    mut button_query: Query<(&mut Visibility, &mut TextureAtlasSprite, &mut ButtonState), With<Hidable>>,
+    mut button_query: Query<(&mut Visibility, Option<&mut TextureAtlasSprite>, &mut ButtonState), With<Hidable>>,
     human_player: Res<HumanPlayer>,
     current_player_data: Res<CurrentPlayerData>,
 ) {
-    for (mut visibility, mut sprite, mut state) in &mut button_query {
+    for (mut visibility, sprite, mut state) in &mut button_query {
         *visibility = if human_player.color == current_player_data.player { // this is the only relevant part to showing or hiding, the rest is just assurance
             Visibility::Inherited
         } else {
             Visibility::Hidden
         };
-        sprite.color = Color::rgba(1.0, 1.0, 1.0, 0.4); // dim the button by default
-        sprite.index = 0;
+        if let Some(mut sprite) = sprite {
+            sprite.color = Color::rgba(1.0, 1.0, 1.0, 0.4); // dim the button by default
+            sprite.index = 0;
+        }
         *state = ButtonState::NotHovered;
     }
 }
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use crate::constants::*;
+
+/// Shows a short message across the top of the board for a few seconds.
+#[derive(Debug, Event)]
+pub struct NoticeEvent(pub String);
+
+#[derive(Component)]
+struct Notice(Timer);
+
+const NOTICE_SECS: f32 = 3.0;
+
+pub struct NoticePlugin;
+
+impl Plugin for NoticePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<NoticeEvent>()
+            .add_systems(Update, (show_notice, expire_notices).chain())
+            ;
+    }
+}
+
+/// Only the latest notice is shown - any older ones are replaced by it.
+fn show_notice(
+    mut commands: Commands,
+    mut notice_events: EventReader<NoticeEvent>,
+    notices: Query<Entity, With<Notice>>,
+    asset_server: Res<AssetServer>,
+) {
+    if let Some(NoticeEvent(message)) = notice_events.iter().last() {
+        for notice in &notices {
+            commands.entity(notice).despawn();
+        }
+        commands.spawn((
+            Text2dBundle{
+                text: Text::from_section(
+                    message.to_uppercase(),
+                    TextStyle{
+                        font: asset_server.load("Kenney Mini.ttf"),
+                        font_size: 20.0,
+                        color: Color::WHITE,
+                    },
+                ),
+                transform: Transform::from_xyz(0.0, WINDOW_SIZE / 2.0 - TILE_SIZE, Z_UI),
+                ..default()
+            },
+            Notice(Timer::from_seconds(NOTICE_SECS, TimerMode::Once)),
+        ));
+    }
+}
+
+fn expire_notices(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut notices: Query<(Entity, &mut Notice)>,
+) {
+    for (entity, mut notice) in &mut notices {
+        if notice.0.tick(time.delta()).just_finished() {
+            commands.entity(entity).despawn();
+        }
+    }
+}
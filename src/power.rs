@@ -1,9 +1,16 @@
 use bevy::prelude::*;
-use crate::buttons::{ActionEvent, ButtonAction, ButtonSize, ButtonState};
-use crate::components::{CurrentPlayer, Evading, Marble, Player, SelfJumping};
-use crate::constants::{CENTER_INDEX, TILE_BUTTON_SIZE, TILE_SIZE, Z_UI};
-use crate::resources::{CurrentPlayerData, DiceData, GameData, GameState, GameButtonAction, HumanPlayer};
+use bevy::text::Text2dBounds;
+use bevy::window::PrimaryWindow;
+use crate::ai::{discard_power_up, draft_power_up};
+use crate::buttons::{is_in_bounds, mouse_watcher, text_button_bundle, watch_button_state_changes, watch_text_button_state_changes, ActionEvent, ButtonAction, ButtonSize, ButtonState, Disabled};
+use crate::components::{CurrentPlayer, Evading, EvadingHighlight, Frozen, Marble, Player, SelfJumping};
+use crate::constants::{CENTER_INDEX, TILE_BUTTON_SIZE, TILE_SIZE, WINDOW_SIZE, Z_UI};
+use crate::input::{Bindings, InputAction};
+use crate::notice::NoticeEvent;
+use crate::resources::{CurrentPlayerData, DiceData, GameData, GameSettings, GameState, GameButtonAction, HumanPlayer, UndoData};
 use crate::shared_systems::SharedSystemSet;
+use crate::targeting::{target_kinds, Targeting};
+use crate::turn_setup::MarbleState;
 use rand::thread_rng;
 use rand::distributions::{ Distribution, WeightedIndex };
 
@@ -15,18 +22,33 @@ pub enum PowerEvent {
     Capture{captor: Player, captive: Player},
     Index{player: Player, index: usize, prev_index: usize},
     Use{player: Player, index: usize},
+    /// Puts a power-up back in the slot it was used from (i.e. when aiming it
+    /// was cancelled).
+    Refund{player: Player, index: usize, power_up: PowerUp},
+    /// Throws away a power-up to make room for the one a full bar is holding.
+    Discard{player: Player, index: usize},
+    /// A power-up that needed targets has been aimed and applied, so it can't
+    /// be refunded anymore.
+    Aimed{player: Player},
 }
 
+/// A player's power bar filled up while all of their slots were taken.
+#[derive(Debug, Event)]
+pub struct PowerBarFullEvent(pub Player);
+
 #[derive(Event)]
 pub enum PowerDownEvent {
-    Evading(Player),
+    /// A marble that has stopped evading.
+    Evading(Entity),
     SelfJumping(Player),
+    Frozen(Player),
 }
 
+/// A power-up that was just used, along with the slot it was used from.
 #[derive(Debug, Event)]
-pub struct ActivatePowerUpEvent(pub PowerUp);
+pub struct ActivatePowerUpEvent(pub PowerUp, pub usize);
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PowerUp {
     RollAgain,       // weight = 4
     DoubleDice,      // weight = 4
@@ -34,9 +56,61 @@ pub enum PowerUp {
     SelfJump,        // weight = 2
     CaptureNearest,  // weight = 1
     HomeRun,         // weight = 1
+    Swap,            // weight = 2
+    Freeze,          // weight = 1
+    Shield,          // weight = 2
 }
 
-const POWER_UP_WEIGHTS: [usize; 6] = [4, 4, 3, 2, 1, 1];
+const POWER_UP_WEIGHTS: [usize; 9] = [4, 4, 3, 2, 1, 1, 2, 1, 2];
+
+/// How many turns (counting the one it's used on) each power-up that makes
+/// marbles evade capture lasts. Using one on a marble that's already evading
+/// adds to the turns it has left.
+pub const EVADE_CAPTURE_TURNS: u8 = 4;
+pub const SHIELD_TURNS: u8 = 7;
+
+impl PowerUp {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerUp::RollAgain => "Roll Again",
+            PowerUp::DoubleDice => "Double Dice",
+            PowerUp::EvadeCapture => "Evade Capture",
+            PowerUp::SelfJump => "Self Jump",
+            PowerUp::CaptureNearest => "Capture Nearest",
+            PowerUp::HomeRun => "Home Run",
+            PowerUp::Swap => "Swap",
+            PowerUp::Freeze => "Freeze",
+            PowerUp::Shield => "Shield",
+        }
+    }
+
+    /// What the power-up does when it's used.
+    pub fn description(&self) -> &'static str {
+        match self {
+            PowerUp::RollAgain => "Roll the dice again - any dice you haven't used are lost.",
+            PowerUp::DoubleDice => "Doubles the value of both dice.",
+            PowerUp::EvadeCapture => "Your marbles can't be captured. Stacks with any turns they have left.",
+            PowerUp::SelfJump => "Your marbles can jump over each other (and blockades).",
+            PowerUp::CaptureNearest => "A marble can move straight to the nearest opponent ahead of it and capture it.",
+            PowerUp::HomeRun => "A marble can move straight into any open tile in your home row.",
+            PowerUp::Swap => "One of your marbles on the track trades places with an opponent's marble.",
+            PowerUp::Freeze => "Pick an opponent - they sit out their next turn once their dice are rolled.",
+            PowerUp::Shield => "Pick one of your marbles - it can't be captured. Stacks with any turns it has left.",
+        }
+    }
+
+    /// How long the power-up lasts once it's used.
+    pub fn duration(&self) -> &'static str {
+        match self {
+            PowerUp::RollAgain | PowerUp::Swap => "right away",
+            PowerUp::DoubleDice => "the rest of this roll",
+            PowerUp::EvadeCapture | PowerUp::SelfJump => "this turn and your next 3",
+            PowerUp::CaptureNearest | PowerUp::HomeRun => "your next move",
+            PowerUp::Freeze => "their next turn",
+            PowerUp::Shield => "this turn and your next 6",
+        }
+    }
+}
 
 impl From<usize> for PowerUp {
     fn from(value: usize) -> Self {
@@ -47,6 +121,9 @@ impl From<usize> for PowerUp {
             3 => PowerUp::SelfJump,
             4 => PowerUp::CaptureNearest,
             5 => PowerUp::HomeRun,
+            6 => PowerUp::Swap,
+            7 => PowerUp::Freeze,
+            8 => PowerUp::Shield,
             _ => unreachable!(),
         }
     }
@@ -55,6 +132,33 @@ impl From<usize> for PowerUp {
 #[derive(Resource)]
 struct PowerUpDistribution(pub WeightedIndex<usize>);
 
+/// How many different power-ups are offered in a draft.
+const DRAFT_OPTIONS: usize = 3;
+
+/// The power-ups the human player has been offered in draft mode but hasn't
+/// picked from yet, along with the slot each pick goes in. They're offered
+/// one draft at a time.
+#[derive(Resource, Default)]
+pub struct PowerUpDrafts(pub Vec<(usize, Vec<PowerUp>)>);
+
+/// Picking one of the power-ups offered in a draft.
+#[derive(Clone, Copy, Debug)]
+struct DraftPick(PowerUp);
+
+/// Asks the human player whether to throw away one of their power-ups to make
+/// room for the one their full power bar is holding.
+#[derive(Resource)]
+pub struct DiscardPrompt;
+
+/// Throwing away the power-up in a slot, or `None` to keep them all.
+#[derive(Clone, Copy, Debug)]
+struct DiscardPick(Option<usize>);
+
+/// Part of the picker shown while the human player drafts a power-up or
+/// picks one to discard.
+#[derive(Component)]
+struct PowerUpPicker;
+
 #[derive(Resource)]
 pub struct PowerUpSpriteSheets {
     pub roll_again: Handle<TextureAtlas>,
@@ -63,12 +167,20 @@ pub struct PowerUpSpriteSheets {
     pub self_jump: Handle<TextureAtlas>,
     pub capture_nearest: Handle<TextureAtlas>,
     pub home_run: Handle<TextureAtlas>,
+    pub swap: Handle<TextureAtlas>,
+    pub freeze: Handle<TextureAtlas>,
+    pub shield: Handle<TextureAtlas>,
 }
 
+/// The description shown while hovering over a power-up.
+#[derive(Component)]
+struct PowerUpTooltip;
+
 #[derive(Resource)]
 pub struct PowerUpHighlights {
     pub evading: Handle<Image>,
     pub self_jumping: Handle<Image>,
+    pub frozen: Handle<Image>,
 }
 
 pub struct PowerUpPlugin;
@@ -81,12 +193,26 @@ impl Plugin for PowerUpPlugin {
             .add_event::<PowerEvent>()
             .add_event::<PowerBarEvent>()
             .add_event::<PowerDownEvent>()
+            .add_event::<ActionEvent<DraftPick>>()
+            .add_event::<ActionEvent<DiscardPick>>()
+            .add_event::<PowerBarFullEvent>()
 
             .insert_resource(PowerUpDistribution(WeightedIndex::new(&POWER_UP_WEIGHTS).unwrap()))
+            .init_resource::<PowerUpDrafts>()
 
-            .add_systems(Update, (handle_power_events, generate_power_up, activate_power_up, power_down_event_handler)
+            .add_systems(Update, (handle_power_events, generate_power_up, handle_full_power_bars, activate_power_up, power_down_event_handler, show_power_up_tooltip, show_full_power_bars)
                 .in_set(SharedSystemSet)
             )
+            .add_systems(Update, (animate_power_bars, float_power_gains, fly_power_ups).in_set(SharedSystemSet))
+            // the pickers stay up through every state until the human player picks
+            .add_systems(Update, (
+                (mouse_watcher::<DraftPick>, watch_button_state_changes, keyboard_draft_pick, pick_draft).chain().run_if(drafting),
+                (mouse_watcher::<DiscardPick>, watch_button_state_changes, watch_text_button_state_changes, keyboard_discard_pick, pick_discard).chain().run_if(discarding),
+                show_power_up_picker,
+                ).chain().in_set(SharedSystemSet)
+            )
+            .add_systems(Update, drag_power_up.run_if(in_state(GameState::HumanTurn)))
+            .add_systems(OnEnter(GameState::GameEnd), (clear_power_up_tooltip, clear_pickers, clear_power_gains))
             ;
     }
 }
@@ -96,6 +222,11 @@ pub struct PowerBar {
     pub power: f32,
     pub power_up_count: usize,
     pub origin: f32,
+    /// The power the fill sprite is showing - it catches up to `power` a
+    /// little at a time.
+    shown: f32,
+    /// How many times the bar filled up without the fill sprite showing it yet.
+    fills: usize,
 }
 
 impl PowerBar {
@@ -104,38 +235,68 @@ impl PowerBar {
             power: 0.,
             power_up_count: 0,
             origin,
+            shown: 0.,
+            fills: 0,
         }
     }
+
+    /// The y position of the power fill sprite for the power being shown.
+    pub fn fill_y(&self) -> f32 {
+        // power-fill sprite is 14 x 126 (that 126 represents 10 power points, so 126 / 10 = 12.6 pixels for every point)
+        self.origin + self.shown * 12.6
+    }
+
+    /// The y position of the top of the bar.
+    fn top_y(&self) -> f32 {
+        // the fill sprite is hidden below the bar when it's empty
+        self.origin + 63.0 + MAX_POWER * 12.6
+    }
+
+    /// Shows the current power straight away instead of filling up to it.
+    pub fn snap(&mut self) {
+        self.shown = self.power;
+        self.fills = 0;
+    }
+}
+
+/// How many power points a bar's fill moves each second.
+const FILL_SPEED: f32 = 12.0;
+
+/// Floating text showing the power a move or capture earned (or lost).
+#[derive(Component)]
+struct PowerGain(Timer);
+
+/// A new power-up flying from the power bar into its slot.
+#[derive(Component)]
+struct FlyIn {
+    from: Vec3,
+    to: Vec3,
+    timer: Timer,
 }
 
 pub const MAX_POWER: f32 = 10.0;
 pub const MAX_POWER_UPS: usize = 3;
 
 impl PowerBar {
-    /// Update the power bar and return `true` if it's full.
+    /// Update the power bar and return `true` if it's full. When there's no
+    /// room for another power-up the bar stays full until a slot opens up.
     pub fn update(&mut self, delta: f32) -> bool {
         let new_power = (self.power + delta).max(0.0); // this reads really weird but it means this -> max(self.power + delta, 0.0)
-        if new_power >= MAX_POWER {
-            match self.power_up_count {
-                0 | 1 => {
-                    self.power = (new_power - MAX_POWER).max(0.0); // carry over
-                    self.power_up_count += 1;
-                    true
-                }
-                2 => {
-                    self.power = 0.0; // reset
-                    self.power_up_count += 1;
-                    true
-                }
-                _ => false,
-            }
+        if new_power >= MAX_POWER && self.power_up_count < MAX_POWER_UPS {
+            self.power = new_power - MAX_POWER; // carry over
+            self.power_up_count += 1;
+            self.fills += 1;
+            true
         } else {
-            if self.power_up_count < MAX_POWER_UPS {
-                self.power = new_power;
-            }
+            self.power = new_power.min(MAX_POWER);
             false
         }
     }
+
+    /// Returns `true` if the bar is full but every slot is taken.
+    pub fn is_full(&self) -> bool {
+        self.power_up_count >= MAX_POWER_UPS && self.power >= MAX_POWER
+    }
 }
 
 #[derive(Event)]
@@ -150,8 +311,14 @@ fn handle_power_events(
     mut power_events: EventReader<PowerEvent>,
     mut power_up_events: EventWriter<GeneratePowerUpEvent>,
     mut activate_events: EventWriter<ActivatePowerUpEvent>,
-    mut power_bars: Query<(&mut PowerBar, &mut Transform, &Player)>,
+    mut power_bars: Query<(&mut PowerBar, &Transform, &Player)>,
+    mut full_events: EventWriter<PowerBarFullEvent>,
+    power_up_sprite_sheets: Res<PowerUpSpriteSheets>,
+    human_player: Res<HumanPlayer>,
+    asset_server: Res<AssetServer>,
 ) {
+    // gains for the same bar in one go (like a move that captures) are stacked
+    let mut gains: Vec<Player> = Vec::new();
     for event in power_events.iter() {
         for (player, power) in match event {
             PowerEvent::Capture{ captor, captive } => {
@@ -191,26 +358,104 @@ fn handle_power_events(
             PowerEvent::Use{ player, index } => {
                 let (power_up, power_up_button) = game_data.players.get_mut(&player).unwrap().use_power_up(*index).unwrap();
                 commands.entity(power_up_button).despawn();
-                activate_events.send(ActivatePowerUpEvent(power_up));
-                vec![(player, None)]
+                activate_events.send(ActivatePowerUpEvent(power_up, *index));
+                use_changes(power_up).into_iter().map(|change| (player, change)).collect()
             }
-        } {
-            let (mut bar, mut transform, _) = power_bars.iter_mut().find(|(_, _, &p)| p == *player).unwrap();
-            match power {
-                Some(power) => {
-                    let power_up = bar.update(power);
-                    // power-fill sprite is 14 x 126 (that 126 represents 10 power points, so 126 / 10 = 12.6 pixels for every point)
-                    transform.translation.y = bar.origin + bar.power * 12.6;
-                    if power_up {
-                        power_up_events.send(GeneratePowerUpEvent(*player));
+            PowerEvent::Refund{ player, index, power_up } => {
+                let power_up_button = spawn_power_up_button(&mut commands, &power_up_sprite_sheets, &human_player, *player, *index, *power_up);
+                game_data.players.get_mut(player).unwrap().power_ups[*index] = Some((*power_up, power_up_button));
+                if let Some((mut bar, _, _)) = power_bars.iter_mut().find(|(_, _, &p)| p == *player) {
+                    bar.power_up_count += 1;
+                }
+                vec![]
+            }
+            PowerEvent::Discard{ player, index } => {
+                if let Some((_, power_up_button)) = game_data.players.get_mut(player).unwrap().use_power_up(*index) {
+                    commands.entity(power_up_button).despawn();
+                    if let Some((mut bar, _, _)) = power_bars.iter_mut().find(|(_, _, &p)| p == *player) {
+                        bar.power_up_count -= 1;
                     }
                 }
-                None => { bar.power_up_count -= 1; }
+                // a full bar pays out into the slot that was just freed up
+                vec![(player, Some(0.0))]
+            }
+            PowerEvent::Aimed{ player } => vec![(player, Some(0.0))],
+        } {
+            let (mut bar, transform, _) = power_bars.iter_mut().find(|(_, _, &p)| p == *player).unwrap();
+            let change = change_power_bar(&mut bar, power);
+            if let Some(power) = change.gain {
+                // float the points earned up from the top of the bar
+                let stacked = gains.iter().filter(|p| *p == player).count() as f32;
+                let (label, color) = if power > 0.0 {
+                    (format!("+{}", power_label(power)), Color::rgb(1.0, 0.85, 0.2))
+                } else {
+                    (format!("-{}", power_label(-power)), Color::rgb(1.0, 0.3, 0.3))
+                };
+                commands.spawn((
+                    Text2dBundle{
+                        text: Text::from_section(
+                            label,
+                            TextStyle{
+                                font: asset_server.load("Kenney Mini.ttf"),
+                                font_size: 14.0,
+                                color,
+                            },
+                        ),
+                        transform: Transform::from_xyz(transform.translation.x, bar.top_y() + 8.0 + 14.0 * stacked, Z_UI + 1.0),
+                        ..default()
+                    },
+                    PowerGain(Timer::from_seconds(1.0, TimerMode::Once)),
+                ));
+                gains.push(*player);
+            }
+            if change.earned {
+                power_up_events.send(GeneratePowerUpEvent(*player));
+            } else if change.filled {
+                full_events.send(PowerBarFullEvent(*player));
             }
         }
     }
 }
 
+/// What one change from a power event did to a power bar.
+#[derive(Debug, Default, PartialEq)]
+struct BarChange {
+    /// The bar paid out a power-up.
+    earned: bool,
+    /// The bar just filled up with every slot taken.
+    filled: bool,
+    /// The power gained or lost, if it should float up from the bar.
+    gain: Option<f32>,
+}
+
+/// Applies one change from a power event to a `bar` - `None` frees up a slot
+/// and `Some` adds (or takes away) power.
+fn change_power_bar(bar: &mut PowerBar, change: Option<f32>) -> BarChange {
+    let Some(power) = change else {
+        bar.power_up_count -= 1;
+        return BarChange::default();
+    };
+    let was_full = bar.is_full();
+    let earned = bar.update(power);
+    BarChange{
+        earned,
+        filled: !earned && bar.is_full() && !was_full,
+        // (a full bar holding a power-up can't take any more)
+        gain: (power != 0.0 && !(was_full && bar.is_full())).then_some(power),
+    }
+}
+
+/// What using `power_up` does to the power bar: `None` frees up its slot and
+/// `Some(0.0)` lets a full bar pay out into it. Power-ups that need targets
+/// wait for [`PowerEvent::Aimed`] to pay out, since cancelling puts them back.
+fn use_changes(power_up: PowerUp) -> Vec<Option<f32>> {
+    if target_kinds(power_up).is_empty() {
+        vec![None, Some(0.0)]
+    } else {
+        vec![None]
+    }
+}
+
 fn generate_power_up(
     mut power_up_events: EventReader<GeneratePowerUpEvent>,
     mut game_data: ResMut<GameData>,
@@ -218,58 +463,539 @@ fn generate_power_up(
     mut commands: Commands,
     power_up_sprite_sheets: Res<PowerUpSpriteSheets>,
     human_player: Res<HumanPlayer>,
+    settings: Res<GameSettings>,
+    mut drafts: ResMut<PowerUpDrafts>,
+    mut undo_data: Option<ResMut<UndoData>>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>)>,
+    power_bars: Query<(&PowerBar, &Transform, &Player)>,
+    mut notice_events: EventWriter<NoticeEvent>,
 ) {
     let mut rng = thread_rng();
     for GeneratePowerUpEvent(player) in power_up_events.iter() {
-        // spawn the power up button first
-        let (x, y) = match player {
-            Player::Red => (-6.5, 2.5),
-            Player::Green => (6.5, 2.5),
-            Player::Blue => (6.5, -5.5),
-            Player::Yellow => (-6.5, -5.5),
+        // get the next unused power-up slot (skipping any waiting on a draft)
+        let is_drafting = |i: usize| *player == human_player.color && drafts.0.iter().any(|(slot, _)| *slot == i);
+        let Some(i) = game_data.players.get(player).unwrap().power_ups.iter().enumerate()
+            .position(|(i, p)| p.is_none() && !is_drafting(i))
+        else {
+            unreachable!();
         };
-        // get the next unused power-up slot
-        let i = match game_data.players.get(&player).unwrap().power_ups {
-            [None, _, _] => 0,
-            [_, None, _] => 1,
-            [_, _, None] => 2,
-            _ => unreachable!(),
+
+        let power_up = if settings.draft_power_ups {
+            // offer a few different power-ups to pick from
+            let mut options: Vec<PowerUp> = Vec::new();
+            while options.len() < DRAFT_OPTIONS {
+                let power_up: PowerUp = power_up_dist.0.sample(&mut rng).into();
+                if !options.contains(&power_up) {
+                    options.push(power_up);
+                }
+            }
+            if *player == human_player.color {
+                // undo can't take back a draft that's waiting on a pick, so it can't take back the move that earned it
+                if let Some(undo_data) = undo_data.as_mut() {
+                    undo_data.0 = None;
+                }
+                drafts.0.push((i, options));
+                continue;
+            }
+            let board: Vec<MarbleState> = marbles.iter()
+                .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+                .collect();
+            let power_up = draft_power_up(*player, &board, &options, &settings);
+            notice_events.send(NoticeEvent(format!("{:?} drafted {}", player, power_up.name())));
+            power_up
+        } else {
+            // randomly generate the power up
+            power_up_dist.0.sample(&mut rng).into()
+        };
+
+        let power_up_button = spawn_power_up_button(&mut commands, &power_up_sprite_sheets, &human_player, *player, i, power_up);
+        game_data.players.get_mut(&player).unwrap().power_ups[i] = Some((power_up, power_up_button));
+
+        // the new power-up flies out of the top of the bar into its slot
+        if let Some((bar, transform, _)) = power_bars.iter().find(|(_, _, p)| *p == player) {
+            let from = Vec3::new(transform.translation.x, bar.top_y(), Z_UI + 1.0);
+            commands.entity(power_up_button).insert((
+                Transform::from_translation(from),
+                FlyIn{
+                    from,
+                    to: slot_position(*player, i).extend(Z_UI),
+                    timer: Timer::from_seconds(0.5, TimerMode::Once),
+                },
+            ));
+        }
+    }
+}
+
+/// Power points as they're shown to the player - whole numbers where they can be.
+fn power_label(power: f32) -> String {
+    if power.fract() == 0.0 {
+        format!("{}", power)
+    } else {
+        format!("{:.1}", power)
+    }
+}
+
+/// Moves each bar's fill toward its power. A bar that filled up goes all the
+/// way to the top before starting over from empty.
+fn animate_power_bars(
+    time: Res<Time>,
+    mut power_bars: Query<(&mut PowerBar, &mut Transform)>,
+) {
+    let step = FILL_SPEED * time.delta_seconds();
+    for (mut bar, mut transform) in &mut power_bars {
+        if bar.fills == 0 && bar.shown == bar.power {
+            continue;
+        }
+        if bar.fills > 0 {
+            bar.shown = (bar.shown + step).min(MAX_POWER);
+            if bar.shown >= MAX_POWER {
+                bar.shown = 0.0;
+                bar.fills -= 1;
+            }
+        } else if bar.shown < bar.power {
+            bar.shown = (bar.shown + step).min(bar.power);
+        } else {
+            bar.shown = (bar.shown - step).max(bar.power);
+        }
+        transform.translation.y = bar.fill_y();
+    }
+}
+
+fn float_power_gains(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut gains: Query<(Entity, &mut PowerGain, &mut Transform, &mut Text)>,
+) {
+    for (entity, mut gain, mut transform, mut text) in &mut gains {
+        if gain.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += TILE_SIZE * time.delta_seconds();
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(gain.0.percent_left());
+        }
+    }
+}
+
+fn fly_power_ups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut flying: Query<(Entity, &mut FlyIn, &mut Transform)>,
+) {
+    for (entity, mut fly_in, mut transform) in &mut flying {
+        let t = fly_in.timer.tick(time.delta()).percent();
+        // ease out so it settles into the slot
+        transform.translation = fly_in.from.lerp(fly_in.to, 1.0 - (1.0 - t) * (1.0 - t));
+        if fly_in.timer.finished() {
+            commands.entity(entity).remove::<FlyIn>();
+        }
+    }
+}
+
+fn clear_power_gains(
+    mut commands: Commands,
+    gains: Query<Entity, With<PowerGain>>,
+) {
+    gains.for_each(|e| commands.entity(e).despawn());
+}
+
+/// Where the `player`'s power-up slot `i` sits on the board.
+fn slot_position(player: Player, i: usize) -> Vec2 {
+    let (x, y) = match player {
+        Player::Red => (-6.5, 2.5),
+        Player::Green => (6.5, 2.5),
+        Player::Blue => (6.5, -5.5),
+        Player::Yellow => (-6.5, -5.5),
+    };
+    Vec2::new(x * TILE_SIZE, (y + 1.5 * (i as f32)) * TILE_SIZE)
+}
+
+impl PowerUpSpriteSheets {
+    fn get(&self, power_up: PowerUp) -> Handle<TextureAtlas> {
+        match power_up {
+            PowerUp::RollAgain => self.roll_again.clone(),
+            PowerUp::DoubleDice => self.double_dice.clone(),
+            PowerUp::EvadeCapture => self.evade_capture.clone(),
+            PowerUp::SelfJump => self.self_jump.clone(),
+            PowerUp::CaptureNearest => self.capture_nearest.clone(),
+            PowerUp::HomeRun => self.home_run.clone(),
+            PowerUp::Swap => self.swap.clone(),
+            PowerUp::Freeze => self.freeze.clone(),
+            PowerUp::Shield => self.shield.clone(),
+        }
+    }
+}
+
+/// What clicking the power-up in the `player`'s slot `i` does.
+fn slot_action(player: Player, i: usize) -> ButtonAction<GameButtonAction> {
+    ButtonAction(ActionEvent(match i {
+        0 => GameButtonAction::PowerUpOne(player),
+        1 => GameButtonAction::PowerUpTwo(player),
+        2 => GameButtonAction::PowerUpThree(player),
+        _ => unreachable!(),
+    }))
+}
+
+/// Spawns the button for a `power_up` in the `player`'s slot `i`.
+fn spawn_power_up_button(
+    commands: &mut Commands,
+    power_up_sprite_sheets: &PowerUpSpriteSheets,
+    human_player: &HumanPlayer,
+    player: Player,
+    i: usize,
+    power_up: PowerUp,
+) -> Entity {
+    let position = slot_position(player, i);
+    let sprite_sheet = SpriteSheetBundle{
+        texture_atlas: power_up_sprite_sheets.get(power_up),
+        transform: Transform::from_translation(position.extend(Z_UI)),
+        ..default()
+    };
+    let action = slot_action(player, i);
+
+    if human_player.color == player {
+        // only want to add button state and size if this is for the human player - we don't want them interacting with the computer players' buttons
+        commands.spawn((
+            sprite_sheet,
+            action,
+            ButtonState::NotHovered,
+            ButtonSize(TILE_BUTTON_SIZE)
+        )).id()
+    } else {
+        commands.spawn((sprite_sheet, action)).id()
+    }
+}
+
+fn drafting(drafts: Res<PowerUpDrafts>) -> bool {
+    !drafts.0.is_empty()
+}
+
+/// The power-up keys pick from the draft too.
+fn keyboard_draft_pick(
+    mut input_actions: EventReader<InputAction>,
+    drafts: Res<PowerUpDrafts>,
+    mut action_events: EventWriter<ActionEvent<DraftPick>>,
+) {
+    let Some((_, options)) = drafts.0.first() else {
+        return;
+    };
+    for input_action in input_actions.iter() {
+        if let InputAction::PowerUp(i) = input_action {
+            if let Some(power_up) = options.get(*i) {
+                action_events.send(ActionEvent(DraftPick(*power_up)));
+            }
+        }
+    }
+}
+
+/// Puts the power-up the human player picked in the slot it was drafted for.
+fn pick_draft(
+    mut commands: Commands,
+    mut action_events: EventReader<ActionEvent<DraftPick>>,
+    mut drafts: ResMut<PowerUpDrafts>,
+    mut game_data: ResMut<GameData>,
+    power_up_sprite_sheets: Res<PowerUpSpriteSheets>,
+    human_player: Res<HumanPlayer>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    // only the first pick counts - the picker is gone after it
+    let Some(ActionEvent(DraftPick(power_up))) = action_events.iter().next().copied() else {
+        return;
+    };
+    action_events.clear();
+    let (slot, _) = drafts.0.remove(0);
+    let player = human_player.color;
+    let power_up_button = spawn_power_up_button(&mut commands, &power_up_sprite_sheets, &human_player, player, slot, power_up);
+    game_data.players.get_mut(&player).unwrap().power_ups[slot] = Some((power_up, power_up_button));
+    notice_events.send(NoticeEvent(format!("{:?} drafted {}", player, power_up.name())));
+}
+
+/// The human player's power bar is full with nowhere to put the power-up - ask
+/// them whether to throw one away. Computer players decide right away.
+fn handle_full_power_bars(
+    mut commands: Commands,
+    mut full_events: EventReader<PowerBarFullEvent>,
+    game_data: Res<GameData>,
+    human_player: Res<HumanPlayer>,
+    settings: Res<GameSettings>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>)>,
+    mut undo_data: Option<ResMut<UndoData>>,
+    mut power_events: EventWriter<PowerEvent>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    for PowerBarFullEvent(player) in full_events.iter() {
+        if *player == human_player.color {
+            // undo can't close the prompt, so it can't take back the move that filled the bar
+            if let Some(undo_data) = undo_data.as_mut() {
+                undo_data.0 = None;
+            }
+            commands.insert_resource(DiscardPrompt);
+            notice_events.send(NoticeEvent("Your power-up slots are full".to_string()));
+            continue;
+        }
+        let board: Vec<MarbleState> = marbles.iter()
+            .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+            .collect();
+        let held = game_data.players.get(player).unwrap().power_ups.map(|p| p.map(|(power_up, _)| power_up));
+        if let Some(index) = discard_power_up(*player, &board, &held, &settings) {
+            notice_events.send(NoticeEvent(format!("{:?} discarded {}", player, held[index].unwrap().name())));
+            power_events.send(PowerEvent::Discard{ player: *player, index });
+        }
+    }
+}
+
+/// Makes full power bars pulse so it's clear they can't fill any further.
+fn show_full_power_bars(
+    time: Res<Time>,
+    mut power_bars: Query<(&PowerBar, &mut Sprite)>,
+) {
+    let pulse = (time.elapsed_seconds() * 4.0).sin() * 0.5 + 0.5;
+    for (bar, mut sprite) in &mut power_bars {
+        let color = if bar.is_full() {
+            Color::rgb(1.0, 0.85 + 0.15 * pulse, 0.2 + 0.8 * pulse)
+        } else {
+            Color::WHITE
         };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
+
+/// Whether the human player is being asked what to discard.
+pub fn discarding(prompt: Option<Res<DiscardPrompt>>, drafts: Res<PowerUpDrafts>) -> bool {
+    // drafts come first since they fill the slots
+    prompt.is_some() && drafts.0.is_empty()
+}
+
+/// The power-up keys pick which power-up to discard, and cancel keeps them all.
+fn keyboard_discard_pick(
+    mut input_actions: EventReader<InputAction>,
+    game_data: Res<GameData>,
+    human_player: Res<HumanPlayer>,
+    mut action_events: EventWriter<ActionEvent<DiscardPick>>,
+) {
+    let power_ups = game_data.players.get(&human_player.color).unwrap().power_ups;
+    for input_action in input_actions.iter() {
+        match input_action {
+            InputAction::PowerUp(i) if power_ups.get(*i).is_some_and(|p| p.is_some()) => {
+                action_events.send(ActionEvent(DiscardPick(Some(*i))));
+            }
+            InputAction::Cancel => action_events.send(ActionEvent(DiscardPick(None))),
+            _ => {}
+        }
+    }
+}
+
+fn pick_discard(
+    mut commands: Commands,
+    mut action_events: EventReader<ActionEvent<DiscardPick>>,
+    human_player: Res<HumanPlayer>,
+    mut power_events: EventWriter<PowerEvent>,
+) {
+    // only the first pick counts - the picker is gone after it
+    let Some(ActionEvent(DiscardPick(index))) = action_events.iter().next().copied() else {
+        return;
+    };
+    action_events.clear();
+    commands.remove_resource::<DiscardPrompt>();
+    if let Some(index) = index {
+        power_events.send(PowerEvent::Discard{ player: human_player.color, index });
+    }
+}
 
-        // randomly generate the power up
-        let power_up: PowerUp = power_up_dist.0.sample(&mut rng).into();
-
-        let sprite_sheet = SpriteSheetBundle{
-            texture_atlas: match power_up {
-                PowerUp::RollAgain => power_up_sprite_sheets.roll_again.clone(),
-                PowerUp::DoubleDice => power_up_sprite_sheets.double_dice.clone(),
-                PowerUp::EvadeCapture => power_up_sprite_sheets.evade_capture.clone(),
-                PowerUp::SelfJump => power_up_sprite_sheets.self_jump.clone(),
-                PowerUp::CaptureNearest => power_up_sprite_sheets.capture_nearest.clone(),
-                PowerUp::HomeRun => power_up_sprite_sheets.home_run.clone(),
+/// Shows the power-ups offered in the human player's next draft across from
+/// the slot the pick goes in. Once there are no drafts left it shows the
+/// discard picker, if the human player's power bar is full.
+fn show_power_up_picker(
+    mut commands: Commands,
+    drafts: Res<PowerUpDrafts>,
+    prompt: Option<Res<DiscardPrompt>>,
+    game_data: Res<GameData>,
+    pickers: Query<Entity, With<PowerUpPicker>>,
+    power_up_sprite_sheets: Res<PowerUpSpriteSheets>,
+    human_player: Res<HumanPlayer>,
+    asset_server: Res<AssetServer>,
+    mut prompted: Local<bool>,
+) {
+    if !drafts.is_changed() && prompt.is_some() == *prompted {
+        return;
+    }
+    *prompted = prompt.is_some();
+    pickers.for_each(|e| commands.entity(e).despawn());
+
+    let font = asset_server.load("Kenney Mini.ttf");
+    if let Some((slot, options)) = drafts.0.first() {
+        let options: Vec<_> = options.iter().map(|p| (*p, DraftPick(*p))).collect();
+        spawn_picker(&mut commands, &power_up_sprite_sheets, font, "PICK A POWER-UP", slot_position(human_player.color, *slot), &options, None);
+    } else if prompt.is_some() {
+        let options: Vec<_> = game_data.players.get(&human_player.color).unwrap().power_ups.iter().enumerate()
+            .filter_map(|(i, p)| p.map(|(power_up, _)| (power_up, DiscardPick(Some(i)))))
+            .collect();
+        spawn_picker(&mut commands, &power_up_sprite_sheets, font, "SLOTS FULL - DISCARD ONE?", slot_position(human_player.color, 1), &options, Some(DiscardPick(None)));
+    }
+}
+
+/// Spawns a picker with a button for each of the `options`, across from the
+/// power-up slot at `slot`. A "KEEP" button is added for `keep`.
+fn spawn_picker<T: Copy + Send + Sync + 'static>(
+    commands: &mut Commands,
+    power_up_sprite_sheets: &PowerUpSpriteSheets,
+    font: Handle<Font>,
+    title: &str,
+    slot: Vec2,
+    options: &[(PowerUp, T)],
+    keep: Option<T>,
+) {
+    // the power-ups sit along the sides of the board, so the picker goes toward the middle
+    let keep_width = if keep.is_some() { TILE_SIZE * 2.5 } else { 0.0 };
+    let size = Vec2::new(TILE_SIZE * (1.5 * options.len() as f32 + 1.0) + keep_width, TILE_SIZE * 2.75);
+    let center = Vec2::new(slot.x - slot.x.signum() * (TILE_SIZE / 2.0 + 4.0 + size.x / 2.0), slot.y);
+    commands.spawn((
+        SpriteBundle{
+            sprite: Sprite{
+                color: Color::rgba(0.0, 0.0, 0.0, 0.85),
+                custom_size: Some(size),
+                ..default()
             },
-            transform: Transform::from_xyz(x * TILE_SIZE, (y + 1.5 * (i as f32)) * TILE_SIZE, Z_UI),
+            transform: Transform::from_translation(center.extend(Z_UI + 0.5)),
             ..default()
+        },
+        PowerUpPicker,
+    ));
+    commands.spawn((
+        Text2dBundle{
+            text: Text::from_section(
+                title,
+                TextStyle{
+                    font: font.clone(),
+                    font_size: 14.0,
+                    color: Color::rgb(1.0, 0.85, 0.2),
+                },
+            ),
+            transform: Transform::from_xyz(center.x, center.y + TILE_SIZE * 0.8, Z_UI + 0.6),
+            ..default()
+        },
+        PowerUpPicker,
+    ));
+    let left = center.x - size.x / 2.0 + TILE_SIZE * 1.25;
+    for (i, (power_up, action)) in options.iter().enumerate() {
+        commands.spawn((
+            SpriteSheetBundle{
+                texture_atlas: power_up_sprite_sheets.get(*power_up),
+                transform: Transform::from_xyz(left + TILE_SIZE * 1.5 * i as f32, center.y - TILE_SIZE * 0.25, Z_UI + 0.6),
+                ..default()
+            },
+            ButtonAction(ActionEvent(*action)),
+            ButtonState::NotHovered,
+            ButtonSize(TILE_BUTTON_SIZE),
+            PowerUpPicker,
+        ));
+    }
+    if let Some(keep) = keep {
+        commands.spawn((
+            text_button_bundle(
+                "KEEP",
+                font,
+                Transform::from_xyz(center.x + size.x / 2.0 - keep_width / 2.0 - TILE_SIZE * 0.25, center.y - TILE_SIZE * 0.25, Z_UI + 0.6),
+                ButtonAction(ActionEvent(keep)),
+                ButtonState::NotHovered,
+                ButtonSize(Vec2::new(TILE_SIZE * 2.0, TILE_SIZE)),
+            ),
+            PowerUpPicker,
+        ));
+    }
+}
+
+fn clear_pickers(
+    mut commands: Commands,
+    mut drafts: ResMut<PowerUpDrafts>,
+    pickers: Query<Entity, With<PowerUpPicker>>,
+) {
+    drafts.0.clear();
+    commands.remove_resource::<DiscardPrompt>();
+    pickers.for_each(|e| commands.entity(e).despawn());
+}
+
+/// Lets the human player reorder their power-ups by dragging one onto another
+/// slot. The two slots trade places (dropping it anywhere else puts it back).
+fn drag_power_up(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    mut game_data: ResMut<GameData>,
+    drafts: Res<PowerUpDrafts>,
+    human_player: Res<HumanPlayer>,
+    mut undo_data: ResMut<UndoData>,
+    mut buttons: Query<(&mut Transform, &mut ButtonAction<GameButtonAction>, &mut ButtonState, &mut TextureAtlasSprite)>,
+    mut pressed: Local<Option<(usize, Vec2, bool)>>,
+) {
+    let player = human_player.color;
+    let window_cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let cursor = window_cursor.map(|pos| Vec2::new(pos.x - WINDOW_SIZE / 2.0, -(pos.y - WINDOW_SIZE / 2.0)));
+    let player_data = game_data.players.get_mut(&player).unwrap();
+    if mouse_button_inputs.just_pressed(bindings.click) {
+        *pressed = window_cursor.zip(cursor).and_then(|(window_cursor, cursor)| (0..MAX_POWER_UPS)
+            .find(|i| player_data.power_ups[*i].is_some() && is_in_bounds(window_cursor, slot_position(player, *i).extend(0.0), TILE_BUTTON_SIZE))
+            .map(|i| (i, cursor, false))
+        );
+    }
+    let Some((from, start, dragging)) = *pressed else {
+        return;
+    };
+    let Some((_, button)) = player_data.power_ups[from] else {
+        *pressed = None;
+        return;
+    };
+
+    if mouse_button_inputs.pressed(bindings.click) {
+        let Some(cursor) = cursor else {
+            return;
         };
-        let action = ButtonAction(ActionEvent(match i {
-            0 => GameButtonAction::PowerUpOne(*player),
-            1 => GameButtonAction::PowerUpTwo(*player),
-            2 => GameButtonAction::PowerUpThree(*player),
-            _ => unreachable!(),
-        }));
+        if !dragging {
+            if cursor.distance(start) < TILE_SIZE / 4.0 {
+                return;
+            }
+            // a dragged power-up can't be used by letting go of it
+            commands.entity(button).insert(Disabled).remove::<FlyIn>();
+            *pressed = Some((from, start, true));
+        }
+        if let Ok((mut transform, ..)) = buttons.get_mut(button) {
+            transform.translation = cursor.extend(Z_UI + 1.0);
+        }
+        return;
+    }
 
-        let power_up_button = if human_player.color == *player {
-            // only want to add button state and size if this is for the human player - we don't want them interacting with the computer players' buttons
-            commands.spawn((
-                sprite_sheet,
-                action,
-                ButtonState::NotHovered,
-                ButtonSize(TILE_BUTTON_SIZE.clone())
-            )).id()
-        } else {
-            commands.spawn((sprite_sheet, action)).id()
+    // the mouse was released
+    *pressed = None;
+    if !dragging {
+        return;
+    }
+    commands.entity(button).remove::<Disabled>();
+    // slots waiting on a draft are spoken for
+    let to = window_cursor.and_then(|window_cursor| (0..MAX_POWER_UPS)
+        .filter(|i| *i != from && !drafts.0.iter().any(|(slot, _)| slot == i))
+        .find(|i| is_in_bounds(window_cursor, slot_position(player, *i).extend(0.0), TILE_BUTTON_SIZE))
+    );
+    if let Some(to) = to {
+        player_data.power_ups.swap(from, to);
+        // keep the move before this one undoable by swapping what it would put back too
+        if let Some((_, power_ups)) = undo_data.0.as_mut().and_then(|undo| undo.power_ups.iter_mut().find(|(p, _)| *p == player)) {
+            power_ups.swap(from, to);
+        }
+    }
+    for i in [from, to.unwrap_or(from)] {
+        let Some((_, button)) = player_data.power_ups[i] else {
+            continue;
         };
-        game_data.players.get_mut(&player).unwrap().power_ups[i] = Some((power_up, power_up_button));
+        if let Ok((mut transform, mut action, mut state, mut sprite)) = buttons.get_mut(button) {
+            transform.translation = slot_position(player, i).extend(Z_UI);
+            *action = slot_action(player, i);
+            *state = ButtonState::NotHovered;
+            sprite.index = 0;
+        }
     }
 }
 
@@ -280,7 +1006,7 @@ fn activate_power_up(
     mut game_data: ResMut<GameData>,
     mut dice_data: ResMut<DiceData>,
     current_player_data: Res<CurrentPlayerData>,
-    mut marbles: Query<Entity, (With<Marble>, With<CurrentPlayer>)>,
+    mut marbles: Query<(Entity, Option<&Evading>), (With<Marble>, With<CurrentPlayer>)>,
     power_up_highlights: Res<PowerUpHighlights>,
 ) {
     let player_data = game_data.players.get_mut(&current_player_data.player).unwrap();
@@ -292,26 +1018,14 @@ fn activate_power_up(
                 Some(GameState::TurnSetup)
             }
             PowerUp::EvadeCapture => {
-                if !player_data.power_up_status.evade_capture() {
-                    for marble in marbles.iter_mut() {
-                        commands.entity(marble).insert(Evading)
-                        .with_children(|parent| {
-                            parent.spawn((
-                                Evading,
-                                SpriteBundle{
-                                    transform: Transform::from_xyz(0., 0., 1.),
-                                    texture: power_up_highlights.evading.clone(),
-                                    ..default()
-                                },
-                            ));
-                        });
-                    }
+                for (marble, evading) in marbles.iter() {
+                    add_evading_turns(&mut commands, marble, evading, EVADE_CAPTURE_TURNS, &power_up_highlights);
                 }
                 None
             }
             PowerUp::SelfJump => {
                 if !player_data.power_up_status.jump_self() {
-                    for marble in marbles.iter_mut() {
+                    for (marble, _) in marbles.iter_mut() {
                         commands.entity(marble).insert(SelfJumping)
                         .with_children(|parent| {
                             parent.spawn((
@@ -335,36 +1049,80 @@ fn activate_power_up(
                 player_data.power_up_status.home_run();
                 Some(GameState::TurnSetup)
             }
+            PowerUp::Swap | PowerUp::Freeze | PowerUp::Shield => {
+                commands.insert_resource(Targeting::new(event.0, event.1));
+                Some(GameState::ChooseTarget)
+            }
         } {
             next_state.set(new_state);
         }
     }
 }
 
+/// Makes a `marble` evade capture for `turns` more turns, adding to any it
+/// already has left.
+pub fn add_evading_turns(
+    commands: &mut Commands,
+    marble: Entity,
+    evading: Option<&Evading>,
+    turns: u8,
+    power_up_highlights: &PowerUpHighlights,
+) {
+    match evading {
+        Some(evading) => {
+            commands.entity(marble).insert(Evading{ turns: evading.turns.saturating_add(turns) });
+        }
+        None => {
+            commands.entity(marble).insert(Evading{ turns })
+            .with_children(|parent| {
+                parent.spawn((
+                    EvadingHighlight,
+                    SpriteBundle{
+                        transform: Transform::from_xyz(0., 0., 1.),
+                        texture: power_up_highlights.evading.clone(),
+                        // see-through so the marble still shows under its shield
+                        sprite: Sprite{ color: Color::rgba(1., 1., 1., 0.6), ..default() },
+                        ..default()
+                    },
+                ));
+            });
+        }
+    }
+}
+
 fn power_down_event_handler(
     mut commands: Commands,
     mut power_down_events: EventReader<PowerDownEvent>,
     marbles: Query<(Entity, &Player), With<Marble>>,
-    evading: Query<(Entity, &Parent), With<Evading>>,
+    evading: Query<(Entity, &Parent), With<EvadingHighlight>>,
     jumping: Query<(Entity, &Parent), With<SelfJumping>>,
+    frost: Query<(Entity, &Parent), With<Frozen>>,
 ) {
     for event in power_down_events.iter() {
         match event {
-            PowerDownEvent::Evading(player) => {
+            PowerDownEvent::Evading(marble) => {
+                commands.entity(*marble).remove::<Evading>();
                 for (highlight_entity, parent) in evading.iter() {
+                    if parent.get() == *marble {
+                        commands.entity(highlight_entity).remove_parent().despawn();
+                    }
+                }
+            }
+            PowerDownEvent::SelfJumping(player) => {
+                for (highlight_entity, parent) in jumping.iter() {
                     if let Ok((marble_entity, marble_player)) = marbles.get(parent.get()) {
                         if player == marble_player {
-                            commands.entity(marble_entity).remove::<Evading>();
+                            commands.entity(marble_entity).remove::<SelfJumping>();
                             commands.entity(highlight_entity).remove_parent().despawn();
                         }
                     }
                 }
             }
-            PowerDownEvent::SelfJumping(player) => {
-                for (highlight_entity, parent) in jumping.iter() {
+            PowerDownEvent::Frozen(player) => {
+                for (highlight_entity, parent) in frost.iter() {
                     if let Ok((marble_entity, marble_player)) = marbles.get(parent.get()) {
                         if player == marble_player {
-                            commands.entity(marble_entity).remove::<SelfJumping>();
+                            commands.entity(marble_entity).remove::<Frozen>();
                             commands.entity(highlight_entity).remove_parent().despawn();
                         }
                     }
@@ -373,3 +1131,183 @@ fn power_down_event_handler(
         }
     }
 }
+
+/// Hovering over any player's power-up shows what it does and how long it
+/// lasts.
+fn show_power_up_tooltip(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    game_data: Res<GameData>,
+    buttons: Query<&Transform>,
+    draft_buttons: Query<(Entity, &ButtonAction<DraftPick>)>,
+    tooltips: Query<Entity, With<PowerUpTooltip>>,
+    asset_server: Res<AssetServer>,
+    mut hovered: Local<Option<Entity>>,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let now_hovered = cursor.and_then(|cursor| game_data.players.values()
+        .flat_map(|data| data.power_ups.iter().flatten().copied())
+        // (the power-ups offered in a draft too)
+        .chain(draft_buttons.iter().map(|(button, action)| (action.0.0.0, button)))
+        .find(|(_, button)| buttons.get(*button).is_ok_and(|t| is_in_bounds(cursor, t.translation, TILE_BUTTON_SIZE)))
+    );
+    // (the tooltip is cleared when the game ends, so show it again if it's gone)
+    if now_hovered.map(|(_, button)| button) == *hovered && (hovered.is_none() || !tooltips.is_empty()) {
+        return;
+    }
+    *hovered = now_hovered.map(|(_, button)| button);
+    tooltips.for_each(|e| commands.entity(e).despawn());
+
+    let Some((power_up, button)) = now_hovered else {
+        return;
+    };
+    let Ok(transform) = buttons.get(button) else {
+        return;
+    };
+    // the power-ups sit along the sides of the board, so the tooltip goes toward the middle
+    let size = Vec2::new(TILE_SIZE * 7.0, TILE_SIZE * 3.5);
+    let x = transform.translation.x - transform.translation.x.signum() * (TILE_SIZE / 2.0 + 4.0 + size.x / 2.0);
+    let y = transform.translation.y;
+    commands.spawn((
+        SpriteBundle{
+            sprite: Sprite{
+                color: Color::rgba(0.0, 0.0, 0.0, 0.85),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_xyz(x, y, Z_UI + 1.0),
+            ..default()
+        },
+        PowerUpTooltip,
+    ));
+    let font = asset_server.load("Kenney Mini.ttf");
+    let style = |color: Color| TextStyle{ font: font.clone(), font_size: 14.0, color };
+    commands.spawn((
+        Text2dBundle{
+            text: Text::from_sections([
+                TextSection::new(format!("{}\n", power_up.name().to_uppercase()), style(Color::rgb(1.0, 0.85, 0.2))),
+                TextSection::new(format!("{}\n", power_up.description()), style(Color::WHITE)),
+                TextSection::new(format!("Lasts {}", power_up.duration()), style(Color::rgb(0.7, 0.7, 0.7))),
+            ]).with_alignment(TextAlignment::Center),
+            text_2d_bounds: Text2dBounds{ size: size - Vec2::splat(8.0) },
+            transform: Transform::from_xyz(x, y, Z_UI + 1.1),
+            ..default()
+        },
+        PowerUpTooltip,
+    ));
+}
+
+fn clear_power_up_tooltip(
+    mut commands: Commands,
+    tooltips: Query<Entity, With<PowerUpTooltip>>,
+) {
+    tooltips.for_each(|e| commands.entity(e).despawn());
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use super::*;
+    use crate::resources::{Dice, PlayerData, PowerUpStatus, UndoMove};
+
+    #[test]
+    fn test_discard_prompt_clears_undo() {
+        let mut app = App::new();
+        app
+            .add_event::<PowerBarFullEvent>()
+            .add_event::<PowerEvent>()
+            .add_event::<NoticeEvent>()
+            .insert_resource(GameData{ players: HashMap::from([(Player::Red, PlayerData::default())]) })
+            .insert_resource(HumanPlayer{ color: Player::Red, human_indicator: Entity::PLACEHOLDER })
+            .insert_resource(GameSettings::default())
+            .insert_resource(UndoData(Some(UndoMove{
+                marble: Entity::PLACEHOLDER,
+                index: 10,
+                prev_index: 4,
+                captured: None,
+                dice: Dice::default(),
+                power_bars: vec![(Player::Red, 8.0, MAX_POWER_UPS)],
+                power_ups: Vec::new(),
+                power_up_status: PowerUpStatus::default(),
+            })))
+            .add_systems(Update, handle_full_power_bars);
+
+        // the move that filled the bar can't be undone once the prompt is up
+        app.world.send_event(PowerBarFullEvent(Player::Red));
+        app.update();
+        assert!(app.world.contains_resource::<DiscardPrompt>());
+        assert!(app.world.resource::<UndoData>().0.is_none());
+    }
+
+    #[test]
+    fn test_power_bar_holds_when_full() {
+        let mut bar = PowerBar::new(0.0);
+        assert!(!bar.update(6.0));
+        assert!(bar.update(5.0));
+        assert_eq!(1.0, bar.power); // carried over
+        bar.power_up_count = MAX_POWER_UPS;
+        assert!(!bar.update(12.0));
+        assert_eq!(MAX_POWER, bar.power);
+        assert!(bar.is_full());
+
+        // a freed slot takes the power-up the bar was holding
+        bar.power_up_count -= 1;
+        assert!(bar.update(0.0));
+        assert_eq!(0.0, bar.power);
+        assert!(!bar.is_full());
+    }
+
+    #[test]
+    fn test_use_pays_out_full_bar() {
+        let full_bar = || {
+            let mut bar = PowerBar::new(0.0);
+            bar.power_up_count = MAX_POWER_UPS;
+            bar.update(MAX_POWER);
+            bar
+        };
+        let earned = |bar: &mut PowerBar, changes: Vec<Option<f32>>| changes.into_iter()
+            .map(|change| change_power_bar(bar, change))
+            .filter(|change| change.earned)
+            .count();
+
+        let mut bar = full_bar();
+        assert_eq!(1, earned(&mut bar, use_changes(PowerUp::RollAgain)));
+        assert_eq!((MAX_POWER_UPS, 0.0), (bar.power_up_count, bar.power));
+
+        // a targeted power-up holds on to the power-up until it's aimed
+        let mut bar = full_bar();
+        assert_eq!(0, earned(&mut bar, use_changes(PowerUp::Swap)));
+        assert_eq!((MAX_POWER_UPS - 1, MAX_POWER), (bar.power_up_count, bar.power));
+        assert!(change_power_bar(&mut bar, Some(0.0)).earned);
+        assert_eq!((MAX_POWER_UPS, 0.0), (bar.power_up_count, bar.power));
+    }
+
+    #[test]
+    fn test_change_power_bar() {
+        let mut bar = PowerBar::new(0.0);
+        bar.power_up_count = MAX_POWER_UPS;
+        assert_eq!(BarChange{ gain: Some(6.0), ..default() }, change_power_bar(&mut bar, Some(6.0)));
+        assert_eq!(BarChange{ filled: true, gain: Some(6.0), ..default() }, change_power_bar(&mut bar, Some(6.0)));
+        // a full bar holding a power-up doesn't show any more gains
+        assert_eq!(BarChange::default(), change_power_bar(&mut bar, Some(3.0)));
+        assert_eq!(BarChange::default(), change_power_bar(&mut bar, None));
+        assert_eq!(BarChange{ earned: true, ..default() }, change_power_bar(&mut bar, Some(0.0)));
+    }
+
+    #[test]
+    fn test_power_bar_fill_animation() {
+        let mut bar = PowerBar::new(0.0);
+        bar.update(12.0);
+        // the fill goes up to the top once before showing what carried over
+        assert_eq!((0.0, 1), (bar.shown, bar.fills));
+        bar.snap();
+        assert_eq!((2.0, 0), (bar.shown, bar.fills));
+    }
+
+    #[test]
+    fn test_power_label() {
+        assert_eq!("3", power_label(3.0));
+        assert_eq!("1.5", power_label(1.5));
+        assert_eq!("0.6", power_label(10.0 * 3.0 / 48.0));
+    }
+}
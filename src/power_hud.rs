@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::shared_systems::SharedSystemSet;
+
+/// One line of a player's active power-up list.
+#[derive(Component)]
+struct EffectBadge;
+
+/// Effects that only last for the current move pulse so they aren't missed.
+#[derive(Component)]
+struct Pulsing;
+
+/// The "x2" shown over the dice while they're doubled.
+#[derive(Component)]
+struct DoubleDiceBadge;
+
+/// Shows which power-ups are in effect - a list next to each player's base
+/// and a badge over the dice when they're doubled.
+pub struct PowerHudPlugin;
+
+impl Plugin for PowerHudPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update,
+                (show_active_effects, show_double_dice_badge, pulse_badges)
+                .in_set(SharedSystemSet)
+            )
+            .add_systems(OnEnter(GameState::GameEnd), clear_badges)
+            ;
+    }
+}
+
+/// The labels for a player's active effects, and whether each one pulses.
+/// `evade_turns` is the most turns any of their marbles has left evading.
+fn active_effects(status: &PowerUpStatus, evade_turns: u8) -> Vec<(String, bool)> {
+    let mut effects = Vec::new();
+    if evade_turns > 0 {
+        effects.push((format!("EVADE {}", evade_turns), false));
+    }
+    if status.jump_self_turns > 0 {
+        effects.push((format!("JUMP {}", status.jump_self_turns), false));
+    }
+    if status.capture_nearest {
+        effects.push(("NEAREST".to_string(), true));
+    }
+    if status.home_run {
+        effects.push(("HOME RUN".to_string(), true));
+    }
+    if status.frozen_turns > 0 {
+        effects.push(("FROZEN".to_string(), false));
+    }
+    effects
+}
+
+/// Lists each player's active effects (with the turns they have left) just
+/// beyond their base.
+fn show_active_effects(
+    mut commands: Commands,
+    game_data: Res<GameData>,
+    evading: Query<(&Player, &Evading)>,
+    changed_evading: Query<(), Changed<Evading>>,
+    mut removed_evading: RemovedComponents<Evading>,
+    badges: Query<Entity, With<EffectBadge>>,
+    asset_server: Res<AssetServer>,
+    mut shown: Local<Vec<(Player, Vec<(String, bool)>)>>,
+) {
+    let evading_changed = !changed_evading.is_empty() || removed_evading.iter().count() > 0;
+    if !game_data.is_changed() && !evading_changed {
+        return;
+    }
+    let mut effects: Vec<(Player, Vec<(String, bool)>)> = game_data.players.iter()
+        .map(|(player, data)| {
+            let evade_turns = evading.iter()
+                .filter(|(p, _)| *p == player)
+                .map(|(_, e)| e.turns)
+                .max()
+                .unwrap_or(0);
+            (*player, active_effects(&data.power_up_status, evade_turns))
+        })
+        .filter(|(_, effects)| !effects.is_empty())
+        .collect();
+    effects.sort_by_key(|(player, _)| *player as usize);
+    // the game data changes all the time so only respawn the badges when they'd look different
+    if effects == *shown && badges.iter().count() == effects.iter().map(|(_, e)| e.len()).sum::<usize>() {
+        return;
+    }
+    badges.for_each(|e| commands.entity(e).despawn());
+
+    let font = asset_server.load("Kenney Mini.ttf");
+    for (player, player_effects) in &effects {
+        // this spot is just past red's base, so it's rotated around to the other bases
+        let (x, y) = player.rotate_coords((-4.25, 5.0));
+        let top = y * TILE_SIZE + (player_effects.len() - 1) as f32 * 7.0;
+        for (i, (label, pulsing)) in player_effects.iter().enumerate() {
+            let mut badge = commands.spawn((
+                Text2dBundle{
+                    text: Text::from_section(
+                        label.clone(),
+                        TextStyle{
+                            font: font.clone(),
+                            font_size: 14.0,
+                            color: Color::WHITE,
+                        },
+                    ),
+                    transform: Transform::from_xyz(x * TILE_SIZE, top - i as f32 * 14.0, Z_UI),
+                    ..default()
+                },
+                EffectBadge,
+            ));
+            if *pulsing {
+                badge.insert(Pulsing);
+            }
+        }
+    }
+    *shown = effects;
+}
+
+fn show_double_dice_badge(
+    mut commands: Commands,
+    dice_data: Res<DiceData>,
+    dice: Query<&Die>,
+    badges: Query<Entity, With<DoubleDiceBadge>>,
+    asset_server: Res<AssetServer>,
+) {
+    let doubled = dice_data.dice.multiplier == 2 && !dice_data.dice.is_empty();
+    match (doubled, badges.is_empty()) {
+        (true, true) => {
+            let (Ok(die_1), Ok(die_2)) = (dice.get(dice_data.die_1), dice.get(dice_data.die_2)) else {
+                return;
+            };
+            // above the gap between the dice, out of the way of the doubles warning
+            let between = (die_1.location + die_2.location) / 2.0;
+            commands.spawn((
+                Text2dBundle{
+                    text: Text::from_section(
+                        "x2",
+                        TextStyle{
+                            font: asset_server.load("Kenney Mini.ttf"),
+                            font_size: 24.0,
+                            color: Color::rgb(1.0, 0.85, 0.2),
+                        },
+                    ),
+                    transform: Transform::from_xyz(between.x, between.y + TILE_SIZE, Z_UI),
+                    ..default()
+                },
+                DoubleDiceBadge,
+                Pulsing,
+            ));
+        }
+        (false, false) => badges.for_each(|e| commands.entity(e).despawn()),
+        _ => {}
+    }
+}
+
+fn pulse_badges(
+    time: Res<Time>,
+    mut badges: Query<&mut Text, With<Pulsing>>,
+) {
+    let alpha = 0.65 + 0.35 * (time.elapsed_seconds() * 5.0).sin();
+    for mut text in &mut badges {
+        for section in text.sections.iter_mut() {
+            section.style.color.set_a(alpha);
+        }
+    }
+}
+
+fn clear_badges(
+    mut commands: Commands,
+    badges: Query<Entity, Or<(With<EffectBadge>, With<DoubleDiceBadge>)>>,
+) {
+    badges.for_each(|e| commands.entity(e).despawn());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_active_effects() {
+        assert!(active_effects(&PowerUpStatus::default(), 0).is_empty());
+
+        let mut status = PowerUpStatus::default();
+        status.jump_self();
+        status.capture_nearest();
+        assert_eq!(
+            vec![("EVADE 4".to_string(), false), ("JUMP 4".to_string(), false), ("NEAREST".to_string(), true)],
+            active_effects(&status, 4)
+        );
+        // the one-shot effects are gone once the turn ends
+        status.tick();
+        assert_eq!(vec![("JUMP 3".to_string(), false)], active_effects(&status, 0));
+    }
+
+    #[test]
+    fn test_frozen_effect() {
+        let mut status = PowerUpStatus::default();
+        assert!(!status.freeze());
+        assert_eq!(vec![("FROZEN".to_string(), false)], active_effects(&status, 0));
+        // it's gone after the frozen player's turn
+        assert_eq!(vec![PowerDownType::Frozen], status.tick());
+        assert!(active_effects(&status, 0).is_empty());
+    }
+}
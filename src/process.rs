@@ -24,8 +24,11 @@ fn check_for_capture(
     current_player_marbles: Query<&Marble, With<CurrentPlayer>>,
     mut opponent_marbles: Query<(Entity, &mut Marble, &Transform, &Player), Without<CurrentPlayer>>,
     mut power_events: EventWriter<PowerEvent>,
+    settings: Res<GameSettings>,
+    mut undo_data: ResMut<UndoData>,
 ) {
     let cur = current_player_marbles.get(current_player_data.moved_marble.unwrap()).unwrap();
+    let owner = current_player_data.marble_owner;
 
     // we don't capture in the home row
     if cur.index >= FIRST_HOME_INDEX && cur.index <= LAST_HOME_INDEX {
@@ -35,12 +38,19 @@ fn check_for_capture(
     if let Some((opp_entity, mut opponent_marble, transform, opponent)) = opponent_marbles.iter_mut()
         // do not check opponent marbles in their home row or at their base
         .filter(|(_, opp, _, _)| opp.index < FIRST_HOME_INDEX || opp.index == CENTER_INDEX)
+        // teammates never capture each other
+        .filter(|(_, _, _, p)| !owner.is_teammate(**p, settings.team_play))
         // find an opponent marble at the same index as the marble just moved by the current player
-        .find(|(_, opp, _, p)| Player::is_same_index(current_player_data.player, cur.index, **p, opp.index))
+        .find(|(_, opp, _, p)| Player::is_same_index(owner, cur.index, **p, opp.index))
         // POWERUP: only include non-deflecting marbles
     {
+        // the captured marble has to come back too if the move is undone
+        if let Some(undo) = undo_data.0.as_mut().filter(|undo| Some(undo.marble) == current_player_data.moved_marble) {
+            undo.captured = Some((opp_entity, opponent_marble.index, opponent_marble.prev_index));
+        }
         opponent_marble.index = BOARD.len();
         commands.entity(opp_entity).insert(Moving::new(opponent_marble.origin, transform.translation));
+        // the points go to the player who rolled, even if they moved their partner's marble
         power_events.send(PowerEvent::Capture{ captor: current_player_data.player, captive: *opponent }); 
     }
 }
@@ -66,15 +76,35 @@ fn process_index(
 }
 
 fn check_for_winner(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
-    marbles: Query<&Marble, With<CurrentPlayer>>,
-    current_player_data: Res<CurrentPlayerData>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&CurrentPlayer>)>,
+    mut current_player_data: ResMut<CurrentPlayerData>,
+    settings: Res<GameSettings>,
+    mut undo_data: ResMut<UndoData>,
 ) {
-    if marbles.iter()
-        .any(|m| !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&m.index))
-    {
+    let all_home = |player: Player| marbles.iter()
+        .filter(|(_, _, p, _)| **p == player)
+        .all(|(_, m, _, _)| (FIRST_HOME_INDEX..=settings.last_home_index()).contains(&m.index));
+
+    let owner = current_player_data.marble_owner;
+    if !all_home(owner) {
         // not a winner
         next_state.set(GameState::TurnSetup);
+    } else if settings.team_play && !all_home(owner.partner()) {
+        // not a winner yet - the rest of this turn is used to move the partner's marbles
+        current_player_data.marble_owner = owner.partner();
+        // the finishing move stands since the turn has moved on to the partner's marbles
+        undo_data.0 = None;
+        for (entity, _, player, current) in &marbles {
+            if current.is_some() {
+                commands.entity(entity).remove::<CurrentPlayer>();
+            }
+            if *player == owner.partner() {
+                commands.entity(entity).insert(CurrentPlayer);
+            }
+        }
+        next_state.set(GameState::TurnSetup);
     } else {
         // winner
         println!("winner = {:?}", current_player_data.player);
@@ -2,6 +2,7 @@ use std::collections::HashMap;
 
 use bevy::prelude::*;
 use crate::components::*;
+use crate::constants::{FIRST_HOME_INDEX, MAX_MARBLES};
 use crate::power::{PowerUp, MAX_POWER_UPS};
 
 #[derive(Resource)]
@@ -40,6 +41,11 @@ impl From<(usize, usize, WhichDie)> for MarbleMove {
 #[derive(Debug, Resource)]
 pub struct CurrentPlayerData {
     pub player: Player,
+    /// The player whose marbles are being moved this turn. This is always the
+    /// same as `player` unless it's a team game and all of the current
+    /// player's marbles are already home, in which case they move their
+    /// partner's marbles.
+    pub marble_owner: Player,
     pub possible_moves: Vec<(Entity, MarbleMove)>,
     pub selected_move: Option<MarbleMove>,
     pub selected_marble: Option<Entity>,
@@ -50,6 +56,7 @@ impl CurrentPlayerData {
     pub fn new(player: Player) -> Self {
         Self{
             player,
+            marble_owner: player,
             possible_moves: Vec::new(),
             selected_move: None,
             selected_marble: None,
@@ -81,6 +88,12 @@ impl CurrentPlayerData {
         }
     }
 
+    /// Returns `true` if any of the possible moves use the dice (i.e. not just
+    /// power-up moves).
+    pub fn has_dice_moves(&self) -> bool {
+        self.possible_moves.iter().any(|(_, m)| m.which != WhichDie::Neither)
+    }
+
     pub fn move_marble(&mut self) {
         self.moved_marble = self.selected_marble.take();
     }
@@ -93,7 +106,7 @@ impl CurrentPlayerData {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct Dice {
     pub one: Option<u8>,
     pub two: Option<u8>,
@@ -162,37 +175,57 @@ impl DiceData {
     }
 }
 
+/// Everything needed to take back the human player's last move.
+#[derive(Debug)]
+pub struct UndoMove {
+    pub marble: Entity,
+    pub index: usize,
+    pub prev_index: usize,
+    /// The opponent marble captured by the move along with its index and
+    /// previous index before it was sent back to its base.
+    pub captured: Option<(Entity, usize, usize)>,
+    pub dice: Dice,
+    /// Every player's power bar (power, power-up count) before the move.
+    pub power_bars: Vec<(Player, f32, usize)>,
+    /// Every player's power-ups before the move.
+    pub power_ups: Vec<(Player, [Option<(PowerUp, Entity)>; MAX_POWER_UPS])>,
+    pub power_up_status: PowerUpStatus,
+}
+
+/// The last move made by the human player, if it can still be taken back. This
+/// is cleared as soon as the dice are rolled again, a power-up is used, a full
+/// power bar asks for a discard, or the turn ends.
+#[derive(Debug, Default, Resource)]
+pub struct UndoData(pub Option<UndoMove>);
+
 #[derive(Clone, Copy, Debug)]
 pub enum GameButtonAction {
     Done,
+    Undo,
+    Hint,
+    /// Picks the dice for a move that can be made more than one way.
+    ChooseDie(WhichDie),
     PowerUpOne(Player),
     PowerUpTwo(Player),
     PowerUpThree(Player),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PowerUpStatus {
-    pub evade_capture_turns: u8,
     pub jump_self_turns: u8,
     pub capture_nearest: bool,
     pub home_run: bool,
+    /// Turns this player has to sit out - set by an opponent's freeze.
+    pub frozen_turns: u8,
 }
 
+#[derive(Debug, PartialEq)]
 pub enum PowerDownType {
-    Evading,
     SelfJumping,
-    EvadingAndSelfJumping,
+    Frozen,
 }
 
 impl PowerUpStatus {
-    pub fn evade_capture(&mut self) -> bool {
-        let already_evading = self.evade_capture_turns > 0;
-        // translates to 3 full turns since the first one will get decremented
-        // during the turn it's used
-        self.evade_capture_turns = 4;
-        return already_evading;
-    }
-
     pub fn jump_self(&mut self) -> bool {
         let already_jumping = self.jump_self_turns > 0;
         // translates to 3 full turns since the first one will get decremented
@@ -209,27 +242,31 @@ impl PowerUpStatus {
         self.home_run = true;
     }
 
-    /// Advance power-up counters - return true if counters are exhausted
-    pub fn tick(&mut self) -> Option<PowerDownType> {
+    /// Skips this player's next turn - returns true if they were already frozen.
+    pub fn freeze(&mut self) -> bool {
+        let already_frozen = self.frozen_turns > 0;
+        // unlike the others this is set on another player, so it only has to
+        // last through their next turn
+        self.frozen_turns = 1;
+        already_frozen
+    }
+
+    /// Advance power-up counters - returns the effects whose counters ran out
+    pub fn tick(&mut self) -> Vec<PowerDownType> {
         self.clear_one_shots();
-        let evade_done = if self.evade_capture_turns > 0 {
-            self.evade_capture_turns -= 1;
-            if self.evade_capture_turns == 0 {
-                true
-            } else { false }
-        } else { false } ;
-        let jump_done = if self.jump_self_turns > 0 {
-            self.jump_self_turns -= 1;
-            if self.jump_self_turns == 0 {
-                true
-            } else { false }
-        } else { false };
-        match (evade_done, jump_done) {
-            (true, false) => Some(PowerDownType::Evading),
-            (false, true) => Some(PowerDownType::SelfJumping),
-            (true, true) => Some(PowerDownType::EvadingAndSelfJumping),
-            _ => None,
+        let mut power_downs = Vec::new();
+        for (turns, power_down) in [
+            (&mut self.jump_self_turns, PowerDownType::SelfJumping),
+            (&mut self.frozen_turns, PowerDownType::Frozen),
+        ] {
+            if *turns > 0 {
+                *turns -= 1;
+                if *turns == 0 {
+                    power_downs.push(power_down);
+                }
+            }
         }
+        power_downs
     }
 
     pub fn clear_one_shots(&mut self) {
@@ -241,10 +278,10 @@ impl PowerUpStatus {
 impl Default for PowerUpStatus {
     fn default() -> Self {
         Self {
-            evade_capture_turns: 0,
             jump_self_turns: 0,
             capture_nearest: false,
             home_run: false,
+            frozen_turns: 0,
         }
     }
 }
@@ -253,6 +290,8 @@ impl Default for PowerUpStatus {
 pub struct PlayerData {
     pub turn_move_count: u8,
     pub consecutive_empty_turns: u8,
+    /// How many doubles have been rolled in a row this turn.
+    pub consecutive_doubles: u8,
     pub multiplier: f32,
     pub power_ups: [Option<(PowerUp, Entity)>; MAX_POWER_UPS],
     pub power_up_status: PowerUpStatus,
@@ -263,6 +302,7 @@ impl Default for PlayerData {
         Self {
             turn_move_count: 0,
             consecutive_empty_turns: 0,
+            consecutive_doubles: 0,
             multiplier: 1.0,
             power_ups: [None, None, None],
             power_up_status: PowerUpStatus::default(),
@@ -271,13 +311,14 @@ impl Default for PlayerData {
 }
 
 impl PlayerData {
-    pub fn end_of_turn(&mut self) -> Option<PowerDownType> {
+    pub fn end_of_turn(&mut self) -> Vec<PowerDownType> {
         self.consecutive_empty_turns = if self.turn_move_count > 0 {
             0
         } else {
             self.consecutive_empty_turns + 1
         };
         self.turn_move_count = 0;
+        self.consecutive_doubles = 0;
         self.power_up_status.tick()
     }
 
@@ -298,6 +339,83 @@ pub struct GameData {
     pub players: HashMap<Player, PlayerData>,
 }
 
+/// What rolling doubles does.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DoublesRule {
+    /// Doubles always grant another roll.
+    #[default]
+    RollAgain,
+    /// Doubles always grant another roll, but the third doubles in a row
+    /// sends the player's lead marble back to its base and ends their turn.
+    ThreeStrikes,
+    /// Only the first doubles of a turn grants another roll.
+    OneExtraRoll,
+    /// Doubles never grant another roll.
+    NoReroll,
+}
+
+/// The rules chosen in the settings menu before a game starts.
+#[derive(Resource, Debug)]
+pub struct GameSettings {
+    /// Red/Blue and Green/Yellow play as partners.
+    pub team_play: bool,
+    /// How many marbles each player has - this is also how much of the home
+    /// row is used.
+    pub marble_count: usize,
+    /// A turn can only end once neither die can be used, and when only one die
+    /// can be used it has to be the larger one.
+    pub use_both_dice: bool,
+    /// How many of a player's marbles in a row it takes to block opponents
+    /// from passing - `None` means there are no blockades.
+    pub blockade: Option<usize>,
+    /// What happens when a player rolls doubles.
+    pub doubles: DoublesRule,
+    /// Tint the human player's marbles by how likely they are to be captured.
+    pub threat_overlay: bool,
+    /// Make the human player's move for them when there's only one they can make.
+    pub auto_forced_move: bool,
+    /// End the human player's turn for them (or roll again on doubles) when
+    /// they can't move.
+    pub auto_pass: bool,
+    /// When a power bar fills, offer a few power-ups to pick from instead of
+    /// handing out a random one.
+    pub draft_power_ups: bool,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            team_play: false,
+            marble_count: MAX_MARBLES,
+            use_both_dice: false,
+            blockade: None,
+            doubles: DoublesRule::default(),
+            threat_overlay: false,
+            auto_forced_move: false,
+            auto_pass: false,
+            draft_power_ups: false,
+        }
+    }
+}
+
+impl GameSettings {
+    /// Returns `true` if the player gets to roll again after this roll.
+    pub fn rolls_again(&self, dice: &Dice, player_data: &PlayerData) -> bool {
+        match self.doubles {
+            DoublesRule::RollAgain | DoublesRule::ThreeStrikes => dice.doubles,
+            DoublesRule::OneExtraRoll => dice.doubles && player_data.consecutive_doubles < 2,
+            DoublesRule::NoReroll => false,
+        }
+    }
+
+    /// The last home row index used with the chosen number of marbles. The
+    /// home row is always filled from the start, so with fewer marbles the
+    /// end of the home row is left unused.
+    pub fn last_home_index(&self) -> usize {
+        FIRST_HOME_INDEX + self.marble_count - 1
+    }
+}
+
 #[derive(States, Default, Debug, Clone, Eq, PartialEq, Hash, Copy)]
 pub enum GameState {
     #[default]
@@ -309,6 +427,8 @@ pub enum GameState {
     TurnSetup,
     ComputerTurn,
     HumanTurn,
+    /// The human player picks what their power-up is used on.
+    ChooseTarget,
     WaitForAnimation,
     ProcessMove,
     EndTurn,
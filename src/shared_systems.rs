@@ -30,8 +30,8 @@ pub fn animate_marble_moves(
 
         // we've arrived if the direction to the destination has flipped, meaning we've passed it
         let arrived = {
-            let d = (moving.destination - transform.translation).normalize();
-            Vec2::new(d.x, d.y).dot(moving.direction) < 0.0
+            let d = (moving.destination - transform.translation).normalize_or_zero();
+            Vec2::new(d.x, d.y).dot(moving.direction) <= 0.0
         };
 
         if arrived {
@@ -86,7 +86,7 @@ pub fn highlighter(
 
                 let rotated_transform_fn = |index| {
                     let tile: (i32, i32) = BOARD[index];
-                    let (x, y) = current_player_data.player.rotate_coords((tile.0 as f32, tile.1 as f32));
+                    let (x, y) = current_player_data.marble_owner.rotate_coords((tile.0 as f32, tile.1 as f32));
                     Transform::from_xyz(x * TILE_SIZE, y * TILE_SIZE, Z_SELECTION_HIGHLIGHT)
                 };
 
@@ -144,7 +144,7 @@ pub fn wait_for_marble_animation(
     current_player_data: Res<CurrentPlayerData>,
 ) {
     for done_event in animation_done_events.iter() {
-        if done_event.0 == current_player_data.player {
+        if done_event.0 == current_player_data.marble_owner {
             next_state.set(GameState::ProcessMove);
         }
     }
@@ -0,0 +1,604 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::ai::choose_target;
+use crate::buttons::is_in_bounds;
+use crate::components::*;
+use crate::constants::*;
+use crate::input::{Bindings, InputAction};
+use crate::notice::NoticeEvent;
+use crate::power::{add_evading_turns, PowerEvent, PowerUp, PowerUpHighlights, SHIELD_TURNS};
+use crate::resources::*;
+use crate::shared_systems::HighlightEvent;
+use crate::turn_setup::MarbleState;
+
+/// Something a power-up can be aimed at.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Marble(Entity),
+    /// A board index, in the terms of the player using the power-up.
+    Tile(usize),
+    Player(Player),
+}
+
+/// What kind of [`Target`] a pick has to be.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TargetKind {
+    Marble,
+    // no power-up is aimed at a tile yet
+    #[allow(dead_code)]
+    Tile,
+    Player,
+}
+
+/// The parts of the game the targeting rules look at.
+pub struct TargetContext<'a> {
+    /// The player whose marbles are being moved this turn.
+    pub owner: Player,
+    pub board: &'a [MarbleState],
+    /// The players that are already frozen.
+    pub frozen: Vec<Player>,
+    pub settings: &'a GameSettings,
+}
+
+impl<'a> TargetContext<'a> {
+    pub fn new(owner: Player, board: &'a [MarbleState], game_data: &GameData, settings: &'a GameSettings) -> Self {
+        let frozen = game_data.players.iter()
+            .filter(|(_, data)| data.power_up_status.frozen_turns > 0)
+            .map(|(player, _)| *player)
+            .collect();
+        Self{ owner, board, frozen, settings }
+    }
+
+    pub fn marble(&self, entity: Entity) -> Option<&MarbleState> {
+        self.board.iter().find(|m| m.entity == entity)
+    }
+
+    pub fn is_opponent(&self, player: Player) -> bool {
+        player != self.owner && !self.owner.is_teammate(player, self.settings.team_play)
+    }
+}
+
+/// A power-up that's being aimed - it goes off once every pick is made.
+#[derive(Resource)]
+pub struct Targeting {
+    pub power_up: PowerUp,
+    /// The slot the power-up was used from - cancelling puts it back there.
+    pub slot: usize,
+    /// The targets picked so far, in the order they were picked.
+    pub picked: Vec<Target>,
+    /// The candidate the keyboard or gamepad is on.
+    focused: Option<Target>,
+    /// Set once the power-up has done its thing (or was cancelled).
+    done: bool,
+}
+
+impl Targeting {
+    pub fn new(power_up: PowerUp, slot: usize) -> Self {
+        Self{ power_up, slot, picked: Vec::new(), focused: None, done: false }
+    }
+
+    /// What the player is asked to pick next.
+    fn prompt(&self) -> &'static str {
+        match (self.power_up, self.picked.len()) {
+            (PowerUp::Swap, 0) => "Pick one of your marbles to swap",
+            (PowerUp::Swap, _) => "Pick an opponent's marble to trade places with",
+            (PowerUp::Freeze, _) => "Pick an opponent to freeze - any of their marbles will do",
+            (PowerUp::Shield, _) => "Pick one of your marbles to shield",
+            _ => "Pick a target",
+        }
+    }
+}
+
+/// Sent by whoever is aiming the power-up - the human player or the computer.
+#[derive(Debug, Event)]
+pub enum TargetEvent {
+    Pick(Target),
+    Cancel,
+}
+
+/// Marks the highlights over the targets that can be picked.
+#[derive(Component)]
+struct TargetHighlight;
+
+pub struct TargetingPlugin;
+
+impl Plugin for TargetingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<TargetEvent>()
+            .add_systems(OnEnter(GameState::ChooseTarget), start_targeting)
+            .add_systems(Update,
+                (human_pick_target, computer_pick_target, apply_target_events, highlight_targets, finish_targeting).chain()
+                .run_if(in_state(GameState::ChooseTarget))
+            )
+            .add_systems(OnExit(GameState::ChooseTarget), stop_targeting)
+            ;
+    }
+}
+
+/// The kind of target for each pick a power-up needs, in order. Power-ups
+/// that aren't aimed need none.
+pub fn target_kinds(power_up: PowerUp) -> &'static [TargetKind] {
+    match power_up {
+        PowerUp::Swap => &[TargetKind::Marble, TargetKind::Marble],
+        PowerUp::Freeze => &[TargetKind::Player],
+        PowerUp::Shield => &[TargetKind::Marble],
+        _ => &[],
+    }
+}
+
+/// Checks whether `target` can be the next pick for the `power_up` after the
+/// ones already `picked`. The error explains why it can't.
+pub fn validate(power_up: PowerUp, picked: &[Target], target: Target, ctx: &TargetContext) -> Result<(), &'static str> {
+    match (power_up, picked, target) {
+        // swap: one of your marbles on the track, then an opponent's that isn't evading
+        (PowerUp::Swap, [], Target::Marble(entity)) => {
+            let marble = ctx.marble(entity).ok_or("Pick a marble")?;
+            if marble.player != ctx.owner {
+                Err("Pick one of your own marbles first")
+            } else if marble.index >= FIRST_HOME_INDEX {
+                Err("Only marbles on the track can be swapped")
+            } else if candidates(power_up, &[target], ctx).is_empty() {
+                Err("There's no opponent marble to swap with")
+            } else {
+                Ok(())
+            }
+        }
+        (PowerUp::Swap, [_], Target::Marble(entity)) => {
+            let marble = ctx.marble(entity).ok_or("Pick a marble")?;
+            if !ctx.is_opponent(marble.player) {
+                Err("Pick an opponent's marble")
+            } else if marble.index >= FIRST_HOME_INDEX {
+                Err("Only marbles on the track can be swapped")
+            } else if marble.evading {
+                Err("Evading marbles can't be swapped")
+            } else {
+                Ok(())
+            }
+        }
+        // shield: one of your marbles that could be captured
+        (PowerUp::Shield, [], Target::Marble(entity)) => {
+            let marble = ctx.marble(entity).ok_or("Pick a marble")?;
+            if marble.player != ctx.owner {
+                Err("Pick one of your own marbles")
+            } else if !(marble.index < FIRST_HOME_INDEX || marble.index == CENTER_INDEX) {
+                Err("Only marbles on the track or in the center can be captured")
+            } else {
+                Ok(())
+            }
+        }
+        // freeze: any opponent who isn't already frozen
+        (PowerUp::Freeze, [], Target::Player(player)) => {
+            if !ctx.is_opponent(player) {
+                Err("Pick an opponent to freeze")
+            } else if ctx.frozen.contains(&player) {
+                Err("They're already frozen")
+            } else {
+                Ok(())
+            }
+        }
+        _ => Err("That can't be picked"),
+    }
+}
+
+/// Every target that would pass [`validate`] as the next pick.
+pub fn candidates(power_up: PowerUp, picked: &[Target], ctx: &TargetContext) -> Vec<Target> {
+    let Some(kind) = target_kinds(power_up).get(picked.len()) else {
+        return Vec::new();
+    };
+    let targets: Vec<Target> = match kind {
+        TargetKind::Marble => ctx.board.iter().map(|m| Target::Marble(m.entity)).collect(),
+        TargetKind::Tile => (0..BOARD.len()).map(Target::Tile).collect(),
+        TargetKind::Player => [Player::Red, Player::Green, Player::Blue, Player::Yellow].into_iter()
+            .filter(|p| ctx.board.iter().any(|m| m.player == *p))
+            .map(Target::Player)
+            .collect(),
+    };
+    targets.into_iter()
+        .filter(|t| validate(power_up, picked, *t, ctx).is_ok())
+        .collect()
+}
+
+/// The new indexes for `a` and `b` once they've traded places (each in its
+/// own player's terms).
+pub fn swap_indexes(a: &MarbleState, b: &MarbleState) -> (usize, usize) {
+    (
+        Player::shift_index(b.index, b.player, a.player),
+        Player::shift_index(a.index, a.player, b.player),
+    )
+}
+
+fn board_state(marbles: &Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>) -> Vec<MarbleState> {
+    marbles.iter()
+        .map(|(entity, m, p, _, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+        .collect()
+}
+
+/// Where a tile is on the screen for the `owner`.
+fn tile_position(owner: Player, index: usize) -> Vec2 {
+    let (x, y) = owner.rotate_coords((BOARD[index].0 as f32, BOARD[index].1 as f32));
+    Vec2::new(x * TILE_SIZE, y * TILE_SIZE)
+}
+
+fn start_targeting(
+    targeting: Res<Targeting>,
+    marbles: Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>,
+    current_player_data: Res<CurrentPlayerData>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    mut computer_turn_timers: ResMut<ComputerTurnTimers>,
+    mut highlight_events: EventWriter<HighlightEvent>,
+    mut notice_events: EventWriter<NoticeEvent>,
+    mut target_events: EventWriter<TargetEvent>,
+) {
+    highlight_events.send(HighlightEvent::Off);
+    computer_turn_timers.reset();
+    let board = board_state(&marbles);
+    let ctx = TargetContext::new(current_player_data.marble_owner, &board, &game_data, &settings);
+    if candidates(targeting.power_up, &targeting.picked, &ctx).is_empty() {
+        notice_events.send(NoticeEvent(format!("There's nothing to {} right now", targeting.power_up.name().to_lowercase())));
+        target_events.send(TargetEvent::Cancel);
+    } else {
+        notice_events.send(NoticeEvent(targeting.prompt().to_string()));
+    }
+}
+
+/// Lets the human player pick with a click, or by moving through the
+/// candidates and confirming with the keyboard or a gamepad. Clicking off the
+/// marbles or pressing cancel puts the power-up back.
+fn human_pick_target(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    mut input_actions: EventReader<InputAction>,
+    mut targeting: ResMut<Targeting>,
+    marbles: Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>,
+    current_player_data: Res<CurrentPlayerData>,
+    human_player: Res<HumanPlayer>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    mut target_events: EventWriter<TargetEvent>,
+) {
+    if targeting.done || human_player.color != current_player_data.player {
+        return;
+    }
+    let Some(kind) = target_kinds(targeting.power_up).get(targeting.picked.len()).copied() else {
+        return;
+    };
+    let owner = current_player_data.marble_owner;
+
+    if mouse_buttons.just_pressed(bindings.click) {
+        if let Some(cursor) = windows.get_single().ok().and_then(|w| w.cursor_position()) {
+            let marble = marbles.iter()
+                .find(|(_, _, _, t, _)| is_in_bounds(cursor, t.translation, TILE_BUTTON_SIZE));
+            let target = match kind {
+                TargetKind::Marble => marble.map(|(e, _, _, _, _)| Target::Marble(e)),
+                TargetKind::Player => marble.map(|(_, _, p, _, _)| Target::Player(*p)),
+                TargetKind::Tile => (0..BOARD.len())
+                    .find(|i| is_in_bounds(cursor, tile_position(owner, *i).extend(0.0), TILE_BUTTON_SIZE))
+                    .map(Target::Tile),
+            };
+            target_events.send(target.map_or(TargetEvent::Cancel, TargetEvent::Pick));
+        }
+    }
+
+    let board = board_state(&marbles);
+    let ctx = TargetContext::new(owner, &board, &game_data, &settings);
+    // (in the order they sit on the board so next/previous go around it)
+    let mut cycle = candidates(targeting.power_up, &targeting.picked, &ctx);
+    cycle.sort_by_key(|t| match t {
+        Target::Marble(e) => {
+            let m = ctx.marble(*e).unwrap();
+            (Player::shift_index(m.index, m.player, owner), e.index() as usize)
+        }
+        Target::Tile(i) => (*i, 0),
+        Target::Player(p) => ((*p as usize + 4 - owner as usize) % 4, 0),
+    });
+    for input_action in input_actions.iter() {
+        let position = cycle.iter().position(|t| Some(*t) == targeting.focused);
+        match *input_action {
+            InputAction::Next | InputAction::NextTarget if !cycle.is_empty() => {
+                targeting.focused = Some(cycle[position.map_or(0, |i| (i + 1) % cycle.len())]);
+            }
+            InputAction::Previous | InputAction::PreviousTarget if !cycle.is_empty() => {
+                targeting.focused = Some(cycle[position.map_or(0, |i| i + cycle.len() - 1) % cycle.len()]);
+            }
+            InputAction::Confirm => {
+                if let Some(target) = targeting.focused {
+                    target_events.send(TargetEvent::Pick(target));
+                }
+            }
+            InputAction::Cancel => target_events.send(TargetEvent::Cancel),
+            _ => {}
+        }
+    }
+}
+
+/// The computer makes its picks one at a time so they can be followed.
+fn computer_pick_target(
+    targeting: Res<Targeting>,
+    marbles: Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>,
+    current_player_data: Res<CurrentPlayerData>,
+    human_player: Res<HumanPlayer>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    time: Res<Time>,
+    mut computer_turn_timers: ResMut<ComputerTurnTimers>,
+    mut target_events: EventWriter<TargetEvent>,
+) {
+    if targeting.done || human_player.color == current_player_data.player {
+        return;
+    }
+    if !computer_turn_timers.move_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    computer_turn_timers.move_timer.reset();
+    let board = board_state(&marbles);
+    let ctx = TargetContext::new(current_player_data.marble_owner, &board, &game_data, &settings);
+    target_events.send(match choose_target(targeting.power_up, &targeting.picked, &ctx) {
+        Some(target) => TargetEvent::Pick(target),
+        None => TargetEvent::Cancel,
+    });
+}
+
+/// Checks each pick, and once they're all made sets off the power-up.
+/// Cancelling refunds the power-up into the slot it came from.
+fn apply_target_events(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut target_events: EventReader<TargetEvent>,
+    mut targeting: ResMut<Targeting>,
+    mut marbles: Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>,
+    current_player_data: Res<CurrentPlayerData>,
+    mut game_data: ResMut<GameData>,
+    settings: Res<GameSettings>,
+    power_up_highlights: Res<PowerUpHighlights>,
+    mut power_events: EventWriter<PowerEvent>,
+    mut notice_events: EventWriter<NoticeEvent>,
+) {
+    for event in target_events.iter() {
+        if targeting.done {
+            return;
+        }
+        let target = match event {
+            TargetEvent::Cancel => {
+                power_events.send(PowerEvent::Refund{
+                    player: current_player_data.player,
+                    index: targeting.slot,
+                    power_up: targeting.power_up,
+                });
+                targeting.done = true;
+                next_state.set(GameState::TurnSetup);
+                return;
+            }
+            TargetEvent::Pick(target) => *target,
+        };
+        let board = board_state(&marbles);
+        let ctx = TargetContext::new(current_player_data.marble_owner, &board, &game_data, &settings);
+        if let Err(reason) = validate(targeting.power_up, &targeting.picked, target, &ctx) {
+            notice_events.send(NoticeEvent(reason.to_string()));
+            continue;
+        }
+        targeting.picked.push(target);
+        targeting.focused = None;
+        if targeting.picked.len() < target_kinds(targeting.power_up).len() {
+            notice_events.send(NoticeEvent(targeting.prompt().to_string()));
+            continue;
+        }
+        targeting.done = true;
+        power_events.send(PowerEvent::Aimed{ player: current_player_data.player });
+
+        match (targeting.power_up, targeting.picked.as_slice()) {
+            (PowerUp::Swap, [Target::Marble(a), Target::Marble(b)]) => {
+                let (Some(a), Some(b)) = (ctx.marble(*a), ctx.marble(*b)) else {
+                    return;
+                };
+                let (new_a, new_b) = swap_indexes(a, b);
+                let Ok([(_, mut marble_a, _, transform_a, _), (_, mut marble_b, _, transform_b, _)]) = marbles.get_many_mut([a.entity, b.entity]) else {
+                    return;
+                };
+                marble_a.update_index(new_a);
+                marble_b.update_index(new_b);
+                commands.entity(a.entity).insert(Moving::new(transform_b.translation, transform_a.translation));
+                commands.entity(b.entity).insert(Moving::new(transform_a.translation, transform_b.translation));
+                notice_events.send(NoticeEvent(format!("{:?} swapped places with {:?}", a.player, b.player)));
+            }
+            (PowerUp::Shield, [Target::Marble(entity)]) => {
+                let Ok((_, _, _, _, evading)) = marbles.get(*entity) else {
+                    return;
+                };
+                add_evading_turns(&mut commands, *entity, evading, SHIELD_TURNS, &power_up_highlights);
+                notice_events.send(NoticeEvent(format!("Shielded for {} turns", SHIELD_TURNS)));
+            }
+            (PowerUp::Freeze, [Target::Player(target)]) => {
+                game_data.players.get_mut(target).unwrap().power_up_status.freeze();
+                for marble in board.iter().filter(|m| m.player == *target) {
+                    commands.entity(marble.entity).insert(Frozen)
+                        .with_children(|parent| {
+                            parent.spawn((
+                                Frozen,
+                                SpriteBundle{
+                                    transform: Transform::from_xyz(0., 0., 1.),
+                                    texture: power_up_highlights.frozen.clone(),
+                                    ..default()
+                                },
+                            ));
+                        });
+                }
+                notice_events.send(NoticeEvent(format!("{:?} is frozen for their next turn", target)));
+            }
+            _ => {}
+        }
+        return;
+    }
+}
+
+/// Rings every target that can be picked (and the ones already picked), with
+/// the focused one brighter.
+fn highlight_targets(
+    mut commands: Commands,
+    targeting: Res<Targeting>,
+    marbles: Query<(Entity, &mut Marble, &Player, &Transform, Option<&Evading>)>,
+    highlights: Query<Entity, With<TargetHighlight>>,
+    current_player_data: Res<CurrentPlayerData>,
+    game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    highlight_data: Res<HighlightData>,
+) {
+    if !targeting.is_changed() {
+        return;
+    }
+    highlights.for_each(|e| commands.entity(e).despawn());
+    if targeting.done {
+        return;
+    }
+    let owner = current_player_data.marble_owner;
+    let board = board_state(&marbles);
+    let ctx = TargetContext::new(owner, &board, &game_data, &settings);
+    let candidates = candidates(targeting.power_up, &targeting.picked, &ctx);
+    for target in targeting.picked.iter().chain(candidates.iter()) {
+        let alpha = if targeting.picked.contains(target) || targeting.focused == Some(*target) { 1.0 } else { 0.5 };
+        let (texture, positions): (_, Vec<Vec2>) = match target {
+            Target::Marble(entity) => (
+                highlight_data.marble_texture.clone(),
+                marbles.get(*entity).map(|(_, _, _, t, _)| t.translation.truncate()).into_iter().collect(),
+            ),
+            // picking a player rings all of their marbles
+            Target::Player(player) => (
+                highlight_data.marble_texture.clone(),
+                marbles.iter().filter(|(_, _, p, _, _)| *p == player).map(|(_, _, _, t, _)| t.translation.truncate()).collect(),
+            ),
+            Target::Tile(index) => (highlight_data.tile_texture.clone(), vec![tile_position(owner, *index)]),
+        };
+        for position in positions {
+            commands.spawn((
+                SpriteBundle{
+                    texture: texture.clone(),
+                    transform: Transform::from_xyz(position.x, position.y, Z_SELECTION_HIGHLIGHT),
+                    sprite: Sprite{ color: Color::rgba(1.0, 1.0, 1.0, alpha), ..default() },
+                    ..default()
+                },
+                TargetHighlight,
+            ));
+        }
+    }
+}
+
+/// Goes back to working out the player's moves once the marbles have settled.
+fn finish_targeting(
+    mut next_state: ResMut<NextState<GameState>>,
+    targeting: Res<Targeting>,
+    moving: Query<(), With<Moving>>,
+) {
+    if targeting.done && !targeting.is_changed() && moving.is_empty() {
+        next_state.set(GameState::TurnSetup);
+    }
+}
+
+fn stop_targeting(
+    mut commands: Commands,
+    highlights: Query<Entity, With<TargetHighlight>>,
+) {
+    highlights.for_each(|e| commands.entity(e).despawn());
+    commands.remove_resource::<Targeting>();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn marble(id: u32, player: Player, index: usize) -> MarbleState {
+        MarbleState{ entity: Entity::from_raw(id), player, index, evading: false }
+    }
+
+    fn context<'a>(board: &'a [MarbleState], settings: &'a GameSettings) -> TargetContext<'a> {
+        TargetContext{ owner: Player::Red, board, frozen: Vec::new(), settings }
+    }
+
+    #[test]
+    fn test_swap_candidates() {
+        let mut board = vec![
+            marble(1, Player::Red, 10),
+            marble(2, Player::Red, FIRST_HOME_INDEX),
+            marble(3, Player::Red, BOARD.len()),
+            marble(4, Player::Green, 20),
+            marble(5, Player::Blue, CENTER_INDEX),
+            marble(6, Player::Yellow, 5),
+        ];
+        board[5].evading = true;
+        let settings = GameSettings::default();
+        let ctx = context(&board, &settings);
+        let own = Target::Marble(Entity::from_raw(1));
+        assert_eq!(vec![own], candidates(PowerUp::Swap, &[], &ctx));
+        assert_eq!(vec![Target::Marble(Entity::from_raw(4))], candidates(PowerUp::Swap, &[own], &ctx));
+        assert_eq!(
+            Err("Evading marbles can't be swapped"),
+            validate(PowerUp::Swap, &[own], Target::Marble(Entity::from_raw(6)), &ctx)
+        );
+
+        // with nobody to swap with there's nothing to pick at all
+        board.remove(3);
+        assert!(candidates(PowerUp::Swap, &[], &context(&board, &settings)).is_empty());
+    }
+
+    #[test]
+    fn test_swap_candidates_skips_teammate() {
+        let board = [marble(1, Player::Red, 10), marble(2, Player::Blue, 20)];
+        let settings = GameSettings{ team_play: true, ..default() };
+        assert!(candidates(PowerUp::Swap, &[], &context(&board, &settings)).is_empty());
+    }
+
+    #[test]
+    fn test_freeze_candidates() {
+        let board = [
+            marble(1, Player::Red, 10),
+            marble(2, Player::Green, BOARD.len()),
+            marble(3, Player::Blue, 20),
+            marble(4, Player::Yellow, FIRST_HOME_INDEX),
+        ];
+        let settings = GameSettings::default();
+        let mut ctx = context(&board, &settings);
+        ctx.frozen.push(Player::Blue);
+        // any opponent with a marble anywhere, but not one that's already frozen
+        assert_eq!(
+            vec![Target::Player(Player::Green), Target::Player(Player::Yellow)],
+            candidates(PowerUp::Freeze, &[], &ctx)
+        );
+        assert_eq!(Err("They're already frozen"), validate(PowerUp::Freeze, &[], Target::Player(Player::Blue), &ctx));
+        assert!(validate(PowerUp::Freeze, &[], Target::Marble(Entity::from_raw(2)), &ctx).is_err());
+    }
+
+    #[test]
+    fn test_shield_candidates() {
+        let mut board = vec![
+            marble(1, Player::Red, 10),
+            marble(2, Player::Red, CENTER_INDEX),
+            marble(3, Player::Red, FIRST_HOME_INDEX),
+            marble(4, Player::Red, BOARD.len()),
+            marble(5, Player::Green, 20),
+        ];
+        // a marble that's already evading can be shielded again to stack the turns
+        board[0].evading = true;
+        let settings = GameSettings::default();
+        assert_eq!(
+            vec![Target::Marble(Entity::from_raw(1)), Target::Marble(Entity::from_raw(2))],
+            candidates(PowerUp::Shield, &[], &context(&board, &settings))
+        );
+    }
+
+    #[test]
+    fn test_untargeted_power_ups() {
+        let board = [marble(1, Player::Red, 10), marble(2, Player::Green, 20)];
+        let settings = GameSettings::default();
+        assert!(target_kinds(PowerUp::DoubleDice).is_empty());
+        assert!(candidates(PowerUp::DoubleDice, &[], &context(&board, &settings)).is_empty());
+    }
+
+    #[test]
+    fn test_swap_indexes() {
+        // red's 13 is green's 1 and green's 20 is red's 32
+        let red = marble(1, Player::Red, 13);
+        let green = marble(2, Player::Green, Player::shift_index(32, Player::Red, Player::Green));
+        assert_eq!((32, Player::shift_index(13, Player::Red, Player::Green)), swap_indexes(&red, &green));
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::BTreeSet;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::ai::{all_rolls, capture_threats};
+use crate::buttons::is_in_bounds;
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::shared_systems::SharedSystemSet;
+use crate::turn_setup::{generate_moves, MarbleState};
+
+/// Marks a tile an opponent's hovered marble can reach with its next roll.
+#[derive(Component)]
+struct ReachMarker;
+
+/// Shows the human player how much danger their marbles are in (only when
+/// the threat overlay setting is on).
+pub struct ThreatOverlayPlugin;
+
+impl Plugin for ThreatOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update,
+                (tint_threatened_marbles, show_reach_on_hover)
+                .in_set(SharedSystemSet)
+                .run_if(threat_overlay_on)
+            )
+            .add_systems(OnEnter(GameState::GameEnd), clear_reach_markers)
+            ;
+    }
+}
+
+fn threat_overlay_on(
+    settings: Res<GameSettings>,
+) -> bool {
+    settings.threat_overlay
+}
+
+fn board_snapshot<'a>(marbles: impl Iterator<Item = (Entity, &'a Marble, &'a Player, Option<&'a Evading>)>) -> Vec<MarbleState> {
+    marbles
+        .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+        .collect()
+}
+
+/// Tints each of the human player's marbles redder the more likely it is to be
+/// captured before their next turn.
+fn tint_threatened_marbles(
+    changed: Query<(), Or<(Changed<Marble>, Added<Evading>)>>,
+    mut removed_evading: RemovedComponents<Evading>,
+    mut marbles: Query<(Entity, &Marble, &Player, Option<&Evading>, &mut Sprite)>,
+    human_player: Res<HumanPlayer>,
+    settings: Res<GameSettings>,
+) {
+    // only recalculate when the board changes
+    if changed.is_empty() && removed_evading.iter().count() == 0 {
+        return;
+    }
+    let board = board_snapshot(marbles.iter().map(|(e, m, p, ev, _)| (e, m, p, ev)));
+    for (entity, _, player, _, mut sprite) in &mut marbles {
+        if *player != human_player.color {
+            continue;
+        }
+        let target = board.iter().find(|m| m.entity == entity).unwrap();
+        // the chance that at least one opponent captures it
+        let chance = 1.0 - capture_threats(target, &board, &settings).iter()
+            .map(|(_, chance)| 1.0 - chance)
+            .product::<f32>();
+        sprite.color = Color::rgb(1.0, 1.0 - chance * 0.8, 1.0 - chance * 0.8);
+    }
+}
+
+/// Hovering over an opponent's marble marks every tile it can reach with its
+/// next roll, with the tiles holding the human player's marbles marked
+/// strongest.
+fn show_reach_on_hover(
+    mut commands: Commands,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>, &Transform)>,
+    moved: Query<(), Changed<Marble>>,
+    markers: Query<Entity, With<ReachMarker>>,
+    human_player: Res<HumanPlayer>,
+    settings: Res<GameSettings>,
+    highlight_data: Res<HighlightData>,
+    mut hovered: Local<Option<Entity>>,
+) {
+    let human = human_player.color;
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let now_hovered = cursor.and_then(|cursor| marbles.iter()
+        .filter(|(_, _, p, _, _)| **p != human && !human.is_teammate(**p, settings.team_play))
+        .find(|(_, _, _, _, t)| is_in_bounds(cursor, t.translation, TILE_BUTTON_SIZE))
+        .map(|(e, _, _, _, _)| e)
+    );
+    // the markers only need to change when the hovered marble or the board does
+    if now_hovered == *hovered && moved.is_empty() {
+        return;
+    }
+    *hovered = now_hovered;
+    markers.for_each(|e| commands.entity(e).despawn());
+
+    let Some(entity) = now_hovered else {
+        return;
+    };
+    let opponent = *marbles.get(entity).unwrap().2;
+    let board = board_snapshot(marbles.iter().map(|(e, m, p, ev, _)| (e, m, p, ev)));
+    let status = PowerUpStatus::default();
+    let reachable: BTreeSet<usize> = all_rolls()
+        .flat_map(|(dice, _)| generate_moves(opponent, &board, &dice, &status, &settings))
+        .filter(|(e, m)| *e == entity && (m.destination < FIRST_HOME_INDEX || m.destination == CENTER_INDEX))
+        .map(|(_, m)| m.destination)
+        .collect();
+    for index in reachable {
+        let yours = board.iter().any(|m| {
+            m.player == human &&
+            (m.index < FIRST_HOME_INDEX || m.index == CENTER_INDEX) &&
+            Player::is_same_index(opponent, index, human, m.index)
+        });
+        let (x, y) = opponent.rotate_coords((BOARD[index].0 as f32, BOARD[index].1 as f32));
+        commands.spawn((
+            SpriteBundle{
+                texture: highlight_data.tile_texture.clone(),
+                sprite: Sprite{
+                    color: if yours { Color::rgb(1.0, 0.2, 0.2) } else { Color::rgba(1.0, 0.4, 0.4, 0.4) },
+                    ..default()
+                },
+                transform: Transform::from_xyz(x * TILE_SIZE, y * TILE_SIZE, Z_SELECTION_HIGHLIGHT),
+                ..default()
+            },
+            ReachMarker,
+        ));
+    }
+}
+
+fn clear_reach_markers(
+    mut commands: Commands,
+    markers: Query<Entity, With<ReachMarker>>,
+) {
+    markers.for_each(|e| commands.entity(e).despawn());
+}
@@ -1,126 +1,142 @@
 use bevy::prelude::*;
 use crate::components::*;
 use crate::constants::*;
+use crate::notice::NoticeEvent;
 use crate::shared_systems::HighlightEvent;
 use crate::resources::*;
 use std::collections::BTreeSet;
 
+/// A marble as the move rules see it. Moves are calculated from a snapshot of
+/// the board like this so we can also look ahead at boards that don't exist
+/// yet.
+#[derive(Clone, Copy, Debug)]
+pub struct MarbleState {
+    pub entity: Entity,
+    pub player: Player,
+    pub index: usize,
+    pub evading: bool,
+}
+
 pub fn calc_possible_moves(
     dice_data: Res<DiceData>,
-    current_player_marbles: Query<(Entity, &Marble), With<CurrentPlayer>>,
-    opponent_marbles: Query<(&Marble, &Player, Option<&Evading>), Without<CurrentPlayer>>,
+    marbles: Query<(Entity, &Marble, &Player, Option<&Evading>)>,
     mut current_player_data: ResMut<CurrentPlayerData>,
     game_data: Res<GameData>,
+    settings: Res<GameSettings>,
+    human_player: Res<HumanPlayer>,
+    mut notice_events: EventWriter<NoticeEvent>,
 ) {
     let player_data = game_data.players.get(&current_player_data.player).unwrap();
+    let owner = current_player_data.marble_owner;
+    let board: Vec<MarbleState> = marbles.iter()
+        .map(|(entity, m, p, ev)| MarbleState{ entity, player: *p, index: m.index, evading: ev.is_some() })
+        .collect();
+
+    let mut possible_moves = generate_moves(owner, &board, &dice_data.dice, &player_data.power_up_status, &settings);
+    if settings.use_both_dice {
+        let (moves, notice) = enforce_both_dice(possible_moves, owner, &board, &dice_data.dice, &player_data.power_up_status, &settings);
+        possible_moves = moves;
+        if let Some(notice) = notice {
+            if human_player.color == current_player_data.player {
+                notice_events.send(NoticeEvent(notice.to_string()));
+            }
+        }
+    }
+    current_player_data.possible_moves = possible_moves;
+}
+
+/// Calculates all of the moves the `owner` can make with their marbles on the
+/// given board.
+pub fn generate_moves(
+    owner: Player,
+    board: &[MarbleState],
+    dice: &Dice,
+    power_up_status: &PowerUpStatus,
+    settings: &GameSettings,
+) -> Vec<(Entity, MarbleMove)> {
+    let last_home_index = settings.last_home_index();
+    let own_marbles: Vec<&MarbleState> = board.iter().filter(|m| m.player == owner).collect();
+    let opponent_marbles: Vec<&MarbleState> = board.iter().filter(|m| m.player != owner).collect();
     let mut possible_moves = BTreeSet::new(); // so we disregard duplicates
 
-    if player_data.power_up_status.home_run {
-        let open_home_indexes: Vec<usize> = (FIRST_HOME_INDEX..=LAST_HOME_INDEX).into_iter()
-            .filter_map(|i| match current_player_marbles.iter().find(|(_, m)| m.index == i) {
+    if power_up_status.home_run {
+        let open_home_indexes: Vec<usize> = (FIRST_HOME_INDEX..=last_home_index).into_iter()
+            .filter_map(|i| match own_marbles.iter().find(|m| m.index == i) {
                 Some(_) => None,
                 None => Some(i),
             })
             .collect();
-        current_player_marbles.iter()
+        own_marbles.iter()
             // home runs are only for marbles that are not already home
-            .filter(|(_, m)| !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&m.index))
+            .filter(|m| !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&m.index))
             // add each open home index as a possible move
-            .for_each(|(e, _)| open_home_indexes.iter().for_each(|&i| {
-                possible_moves.insert((e, vec![i], WhichDie::Neither));
+            .for_each(|m| open_home_indexes.iter().for_each(|&i| {
+                possible_moves.insert((m.entity, vec![i], WhichDie::Neither));
             }));
-        current_player_data.possible_moves = possible_moves.into_iter().map(|(entity, path, which)| {
+        return possible_moves.into_iter().map(|(entity, path, which)| {
             (entity, (*path.last().unwrap(), path.len(), which).into())
         }).collect();
-        return;
     }
 
-    if player_data.power_up_status.capture_nearest {
-        current_player_marbles.iter()
+    if power_up_status.capture_nearest {
+        own_marbles.iter()
             // cannot capture from the base or home
-            .filter(|(_, m)| m.index != BOARD.len() && !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&m.index))
-            .for_each(|(e, m)| {
-                let closest = opponent_marbles.iter()
-                    .filter(|(om, _, ev)| {
-                        ev.is_none() && // can't capture evading marbles
+            .filter(|m| m.index != BOARD.len() && !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&m.index))
+            .for_each(|m| {
+                let paths: Vec<Vec<usize>> = opponent_marbles.iter()
+                    .filter(|om| {
+                        !owner.is_teammate(om.player, settings.team_play) && // can't capture teammates
+                        !om.evading && // can't capture evading marbles
                         om.index != BOARD.len() && // can't capture marbles in the base
                         !(FIRST_HOME_INDEX..=LAST_HOME_INDEX).contains(&om.index) // can't capture marbles in the home row
                     })
                     // map all to shifted indexes
-                    .map(|(om, op, _)| Player::shift_index(om.index, *op, current_player_data.player))
-                    // we can only capture marbles in front of us
-                    .filter(|i| i > &m.index)
-                    // we can only capture in the center if we can enter the center
-                    .filter(|i| *i != CENTER_INDEX || m.index <= 29)
-                    // find the smallest distance between this marble and the opponent marbles
-                    .min_by_key(|index| { // FIXME: we're not considering the possibility of more than one nearest capture
-                        match *index {
-                            // distance to center index depends on where the next entrance is
-                            CENTER_INDEX => match m.index {
-                                18..=29 => 29 - m.index + 1,
-                                6..=17 => 17 - m.index + 1,
-                                0..=5 => 5 - m.index + 1,
-                                _ => usize::MAX,
-                            }
-                            idx => match m.index {
-                                CENTER_INDEX => match idx {
-                                    41..=47 => 47 - idx + 1,
-                                    _ => usize::MAX,
-                                }
-                                _ => idx - m.index,
-                            }
-                        }
-                    });
-                if let Some(index) = closest {
-                    let path = match index {
-                        CENTER_INDEX => {
-                            let mut path: Vec<_> = match m.index {
-                                18..=29 => (m.index..=29).collect(),
-                                6..=17 => (m.index..=17).collect(),
-                                0..=5 => (m.index..=5).collect(),
-                                _ => unreachable!(),
-                            };
-                            path.remove(0); // paths should not contain the current marble location
-                            path.push(CENTER_INDEX);
-                            path
-                        }
-                        i => match m.index {
-                            CENTER_INDEX => (41..=i).collect(),
-                            _ => (m.index + 1..=i).collect(),
-                        }
-                    };
-                    possible_moves.insert((e, path, WhichDie::Neither));
+                    .map(|om| Player::shift_index(om.index, om.player, owner))
+                    // we can only capture marbles we can reach
+                    .filter_map(|i| capture_nearest_path(m.index, i))
+                    .collect();
+                // every opponent at the smallest distance is a possible capture - the player picks which one
+                if let Some(nearest) = paths.iter().map(|path| path.len()).min() {
+                    for path in paths.into_iter().filter(|path| path.len() == nearest) {
+                        possible_moves.insert((m.entity, path, WhichDie::Neither));
+                    }
                 }
             });
     }
 
-    if !dice_data.dice.is_empty() {
-        for (entity, marble) in &current_player_marbles {
+    if !dice.is_empty() {
+        for marble in &own_marbles {
             // exit base
             if marble.index == BOARD.len() {
-                base_exit_rules(&dice_data.dice, entity, &mut possible_moves);
+                base_exit_rules(dice, marble.entity, &mut possible_moves);
                 continue;
             }
 
             // exit center
             if marble.index == CENTER_INDEX {
-                center_exit_rules(&dice_data.dice, entity, &mut possible_moves);
+                center_exit_rules(dice, marble.entity, &mut possible_moves);
                 continue;
             }
 
             // basic moves
-            basic_rules(&dice_data.dice, entity, marble, &mut possible_moves);
+            basic_rules(dice, marble.entity, marble.index, last_home_index, &mut possible_moves);
         }
     }
 
-    // filter out moves that violate the self-hop rules and moves that land on "evading" opponents
-    current_player_data.possible_moves = possible_moves.into_iter()
+    // opponents' blockades can only be crossed by jumping
+    let blockades = match settings.blockade {
+        Some(size) if power_up_status.jump_self_turns == 0 => blockade_tiles(owner, board, size, settings),
+        _ => Vec::new(),
+    };
+
+    // filter out moves that violate the self-hop rules, moves through blockades, and moves that land on "evading" opponents or teammates
+    possible_moves.into_iter()
         .filter_map(|(entity, path, which)| {
-            let self_jump_violations = current_player_marbles.iter()
-                .filter(|(e, _)| *e != entity) // no need to compare the same marbles
-                .find(|(_, other_marble)| {
+            let self_jump_violations = own_marbles.iter()
+                .filter(|m| m.entity != entity) // no need to compare the same marbles
+                .find(|other_marble| {
                     // if we're allowed to jump over our own marbles find one where we land on it
-                    if player_data.power_up_status.jump_self_turns > 0 {
+                    if power_up_status.jump_self_turns > 0 {
                         other_marble.index == *path.last().unwrap()
                     }
                     // look for another one of our marbles along the path of this move
@@ -129,20 +145,146 @@ pub fn calc_possible_moves(
                     }
                 });
             let evading_violations = opponent_marbles.iter()
-                .filter(|(_, _, ev)| ev.is_some())
-                .find(|(m, p, _)| {
-                    Player::is_same_index(current_player_data.player, *path.last().unwrap(), **p, m.index)
+                .filter(|m| m.evading)
+                .find(|m| {
+                    Player::is_same_index(owner, *path.last().unwrap(), m.player, m.index)
                 });
             if evading_violations.is_some() {
                 println!("evading violation: {:?}", (entity, *path.last().unwrap(), which));
             }
-            if self_jump_violations.is_none() && evading_violations.is_none() {
+            let teammate_violations = opponent_marbles.iter()
+                .filter(|m| {
+                    owner.is_teammate(m.player, settings.team_play) &&
+                    (m.index < FIRST_HOME_INDEX || m.index == CENTER_INDEX) // home rows and bases are never shared
+                })
+                .find(|m| {
+                    Player::is_same_index(owner, *path.last().unwrap(), m.player, m.index)
+                });
+            let blockade_violation = path.iter().any(|i| blockades.contains(i));
+            if self_jump_violations.is_none() && evading_violations.is_none() && teammate_violations.is_none() && !blockade_violation {
                 Some((entity, (*path.last().unwrap(), path.len(), which).into()))
             } else {
                 None
             }
         })
-        .collect();
+        .collect()
+}
+
+/// Finds the tiles (as `owner` indexes) covered by opponents' blockades. A
+/// blockade is `size` or more of one opponent's marbles in a row on the main
+/// track.
+fn blockade_tiles(
+    owner: Player,
+    board: &[MarbleState],
+    size: usize,
+    settings: &GameSettings,
+) -> Vec<usize> {
+    const TRACK_LEN: usize = FIRST_HOME_INDEX;
+    let mut tiles = Vec::new();
+    for player in [Player::Red, Player::Green, Player::Blue, Player::Yellow] {
+        if player == owner || owner.is_teammate(player, settings.team_play) {
+            continue;
+        }
+        let occupied: Vec<usize> = board.iter()
+            .filter(|m| m.player == player && m.index < TRACK_LEN)
+            .map(|m| Player::shift_index(m.index, player, owner))
+            .collect();
+        // a tile is part of a blockade if some run of `size` occupied tiles covers it
+        // - the track is a loop so the run can wrap around
+        for &tile in &occupied {
+            let in_blockade = (0..size).any(|offset| {
+                let first = (tile + TRACK_LEN - offset) % TRACK_LEN;
+                (0..size).all(|k| occupied.contains(&((first + k) % TRACK_LEN)))
+            });
+            if in_blockade {
+                tiles.push(tile);
+            }
+        }
+    }
+    tiles
+}
+
+/// Returns a copy of the board with the given marble moved to `destination`,
+/// sending any opponent marble it lands on back to its base.
+pub fn apply_move(
+    owner: Player,
+    board: &[MarbleState],
+    entity: Entity,
+    destination: usize,
+    settings: &GameSettings,
+) -> Vec<MarbleState> {
+    let can_capture = destination < FIRST_HOME_INDEX || destination == CENTER_INDEX;
+    board.iter()
+        .map(|m| {
+            let mut m = *m;
+            if m.entity == entity {
+                m.index = destination;
+            } else if can_capture
+                && m.player != owner
+                && !owner.is_teammate(m.player, settings.team_play)
+                && (m.index < FIRST_HOME_INDEX || m.index == CENTER_INDEX)
+                && Player::is_same_index(owner, destination, m.player, m.index)
+            {
+                m.index = BOARD.len();
+            }
+            m
+        })
+        .collect()
+}
+
+/// Enforces the "must use both dice if possible" rule. Moves that would leave
+/// the other die unusable are removed when some other move lets both dice be
+/// used. When only one die can be used, it has to be the larger one. Power-up
+/// moves that don't use the dice are always left alone.
+///
+/// Returns the allowed moves, along with a message explaining why moves were
+/// removed (if any were).
+fn enforce_both_dice(
+    moves: Vec<(Entity, MarbleMove)>,
+    owner: Player,
+    board: &[MarbleState],
+    dice: &Dice,
+    power_up_status: &PowerUpStatus,
+    settings: &GameSettings,
+) -> (Vec<(Entity, MarbleMove)>, Option<&'static str>) {
+    // the rule only matters before either die has been used
+    let (Some(one), Some(two)) = (dice.one, dice.two) else {
+        return (moves, None);
+    };
+
+    // does this move use both dice, either on its own or by leaving a move for the other die?
+    let uses_both = |(entity, marble_move): &(Entity, MarbleMove)| match marble_move.which {
+        WhichDie::Both => true,
+        WhichDie::Neither => false,
+        which => {
+            let mut remaining = dice.clone();
+            remaining.use_die(which);
+            let next_board = apply_move(owner, board, *entity, marble_move.destination, settings);
+            generate_moves(owner, &next_board, &remaining, power_up_status, settings)
+                .iter().any(|(_, m)| m.which != WhichDie::Neither)
+        }
+    };
+
+    let count = moves.len();
+    let (moves, notice) = if moves.iter().any(uses_both) {
+        let moves: Vec<_> = moves.into_iter()
+            .filter(|m| m.1.which == WhichDie::Neither || uses_both(m))
+            .collect();
+        (moves, "You must use both dice if you can")
+    } else if one != two {
+        let larger = if one > two { WhichDie::One } else { WhichDie::Two };
+        if !moves.iter().any(|(_, m)| m.which == larger) {
+            return (moves, None);
+        }
+        let moves: Vec<_> = moves.into_iter()
+            .filter(|(_, m)| m.which == WhichDie::Neither || m.which == larger)
+            .collect();
+        (moves, "Only one die can be used - it has to be the larger one")
+    } else {
+        return (moves, None);
+    };
+    let notice = if moves.len() < count { Some(notice) } else { None };
+    (moves, notice)
 }
 
 pub fn count_moves(
@@ -171,6 +313,27 @@ pub fn turn_setup_complete(
     }
 }
 
+/// Calculates the path a marble at `start` takes to capture an opponent at
+/// `target` with the capture nearest power-up, or `None` if it can't get
+/// there. Marbles only move forward, so the center is entered from the next
+/// entrance along the way (there isn't one after tile 29) and marbles in the
+/// center can only reach the tiles between the center exit and their home row.
+fn capture_nearest_path(start: usize, target: usize) -> Option<Vec<usize>> {
+    match (start, target) {
+        (CENTER_INDEX, CENTER_INDEX) => None,
+        (CENTER_INDEX, i) if (CENTER_EXIT_INDEX..FIRST_HOME_INDEX).contains(&i) => Some((CENTER_EXIT_INDEX..=i).collect()),
+        (CENTER_INDEX, _) => None,
+        (_, CENTER_INDEX) => {
+            let entrance = CENTER_ENTRANCE_INDEXES.into_iter().find(|e| *e >= start)?;
+            let mut path: Vec<_> = (start + 1..=entrance).collect();
+            path.push(CENTER_INDEX);
+            Some(path)
+        }
+        (_, i) if i > start && i < FIRST_HOME_INDEX => Some((start + 1..=i).collect()),
+        _ => None,
+    }
+}
+
 /// Calculates the path from a starting index into the center index. This will
 /// return `None` if the end index is not one index past a center entrance
 /// index. If a path is returned it requires the use of both dice (i.e. a marble
@@ -185,6 +348,32 @@ fn enter_center_path(start: usize, end: usize) -> Option<Vec<usize>> {
     }
 }
 
+/// Rebuilds the tiles a marble travels over (not including where it starts)
+/// when it moves from `start` to `destination`. Power-up moves that don't use
+/// the dice to reach the home row jump straight there.
+pub fn move_path(start: usize, destination: usize, which: WhichDie) -> Vec<usize> {
+    let home_row = FIRST_HOME_INDEX..=LAST_HOME_INDEX;
+    if which == WhichDie::Neither && home_row.contains(&destination) && !home_row.contains(&start) {
+        return vec![destination];
+    }
+    let first = match start {
+        CENTER_INDEX => CENTER_EXIT_INDEX,
+        i if i == BOARD.len() => START_INDEX,
+        i => i + 1,
+    };
+    if destination == CENTER_INDEX {
+        // the center is entered from the next entrance along the way
+        let entrance = CENTER_ENTRANCE_INDEXES.into_iter()
+            .find(|e| *e >= first)
+            .unwrap_or(first);
+        let mut path: Vec<_> = (first..=entrance).collect();
+        path.push(CENTER_INDEX);
+        path
+    } else {
+        (first..=destination).collect()
+    }
+}
+
 fn base_exit_rules(
     dice: &Dice,
     entity: Entity,
@@ -240,25 +429,26 @@ fn center_exit_rules(
 fn basic_rules(
     dice: &Dice,
     entity: Entity,
-    marble: &Marble,
+    index: usize,
+    last_home_index: usize,
     possible_moves: &mut BTreeSet<(Entity, Vec<usize>, WhichDie)>,
 ) {
     let mut basic_moves = BTreeSet::new();
     match (dice.one, dice.two) {
         (Some(d1), Some(d2)) => {
-            basic_moves.insert((entity, (marble.index + 1..=marble.index + (d1 * dice.multiplier) as usize).collect(), WhichDie::One));
-            basic_moves.insert((entity, (marble.index + 1..=marble.index + (d2 * dice.multiplier) as usize).collect(), WhichDie::Two));
-            basic_moves.insert((entity, (marble.index + 1..=marble.index + ((d1 + d2) * dice.multiplier) as usize).collect(), WhichDie::Both));
+            basic_moves.insert((entity, (index + 1..=index + (d1 * dice.multiplier) as usize).collect(), WhichDie::One));
+            basic_moves.insert((entity, (index + 1..=index + (d2 * dice.multiplier) as usize).collect(), WhichDie::Two));
+            basic_moves.insert((entity, (index + 1..=index + ((d1 + d2) * dice.multiplier) as usize).collect(), WhichDie::Both));
 
-            if let Some(center_path) = enter_center_path(marble.index, marble.index + ((d1 + d2) * dice.multiplier) as usize) {
+            if let Some(center_path) = enter_center_path(index, index + ((d1 + d2) * dice.multiplier) as usize) {
                 basic_moves.insert((entity, center_path, WhichDie::Both));
             }
         }
         (Some(d1), None) => {
-            basic_moves.insert((entity, (marble.index + 1..=marble.index + (d1 * dice.multiplier) as usize).collect(), WhichDie::One));
+            basic_moves.insert((entity, (index + 1..=index + (d1 * dice.multiplier) as usize).collect(), WhichDie::One));
         }
         (None, Some(d2)) => {
-            basic_moves.insert((entity, (marble.index + 1..=marble.index + (d2 * dice.multiplier) as usize).collect(), WhichDie::Two));
+            basic_moves.insert((entity, (index + 1..=index + (d2 * dice.multiplier) as usize).collect(), WhichDie::Two));
         }
         _ => unreachable!(),
     }
@@ -266,10 +456,10 @@ fn basic_rules(
     // filter out moves that don't make sense
     basic_moves = basic_moves.into_iter().filter(|(_, path, _)| {
         let dest = *path.last().unwrap();
-        dest <= LAST_HOME_INDEX // destination must be a valid board space
+        dest <= last_home_index // destination must be a valid board space for the number of marbles being played
             || (dest == CENTER_INDEX // the center space is okay as long as...
                 // ...the marble was not at the end of the home row (this means the path will only be [CENTER_INDEX]) AND...
-                && marble.index != LAST_HOME_INDEX
+                && index != LAST_HOME_INDEX
                 // ...the path doesn't go through the home row
                 && !path.iter().any(|i| *i >= FIRST_HOME_INDEX && *i <= LAST_HOME_INDEX))
     }).collect();
@@ -311,7 +501,7 @@ mod test {
         let dice = Dice::new(5, 5);
         let marble = Marble{ index: 43, prev_index: 42, origin: Vec3::ZERO };
         let mut moves = BTreeSet::new();
-        basic_rules(&dice, Entity::from_raw(12), &marble, &mut moves);
+        basic_rules(&dice, Entity::from_raw(12), marble.index, LAST_HOME_INDEX, &mut moves);
         let mut iter = moves.iter();
         assert_eq!(2, moves.len());
         assert_eq!(vec![44, 45, 46, 47, 48], iter.next().unwrap().1);
@@ -320,9 +510,154 @@ mod test {
         let dice = Dice::new(4, 1);
         let marble = Marble{ index: 52, prev_index: 52, origin: Vec3::ZERO };
         moves = BTreeSet::new();
-        basic_rules(&dice, Entity::from_raw(13), &marble, &mut moves);
+        basic_rules(&dice, Entity::from_raw(13), marble.index, LAST_HOME_INDEX, &mut moves);
         assert_eq!(0, moves.len());
     }
 
-    // TODO: test for capture nearest bug (unreachable code when using capture nearest after tile 29 with an opponent in the center)
+    #[test]
+    fn test_basic_moves_short_home_row() {
+        // with 3 marbles the home row ends at 50
+        let dice = Dice::new(4, 1);
+        let marble = Marble{ index: 47, prev_index: 46, origin: Vec3::ZERO };
+        let mut moves = BTreeSet::new();
+        basic_rules(&dice, Entity::from_raw(12), marble.index, FIRST_HOME_INDEX + 2, &mut moves);
+        assert_eq!(1, moves.len());
+        assert_eq!(vec![48], moves.iter().next().unwrap().1);
+    }
+
+    #[test]
+    fn test_move_path() {
+        assert_eq!(vec![0, 1, 2], move_path(BOARD.len(), 2, WhichDie::Both));
+        assert_eq!(vec![11, 12, 13], move_path(10, 13, WhichDie::One));
+        assert_eq!(vec![15, 16, 17, CENTER_INDEX], move_path(14, CENTER_INDEX, WhichDie::Both));
+        assert_eq!(vec![41, 42], move_path(CENTER_INDEX, 42, WhichDie::Both));
+        assert_eq!(vec![46, 47, 48], move_path(45, 48, WhichDie::Two));
+        assert_eq!(vec![50], move_path(20, 50, WhichDie::Neither)); // home run
+    }
+
+    fn marble(id: u32, player: Player, index: usize) -> MarbleState {
+        MarbleState{ entity: Entity::from_raw(id), player, index, evading: false }
+    }
+
+    #[test]
+    fn test_must_use_both_dice() {
+        // red marble 2 moving 3 to 43 leaves no move for the 6 (it would land on marble 1)
+        let board = [
+            marble(1, Player::Red, 49),
+            marble(2, Player::Red, 40),
+        ];
+        let dice = Dice::new(3, 6);
+        let status = PowerUpStatus::default();
+        let settings = GameSettings::default();
+        let moves = generate_moves(Player::Red, &board, &dice, &status, &settings);
+        assert_eq!(3, moves.len());
+
+        let (moves, notice) = enforce_both_dice(moves, Player::Red, &board, &dice, &status, &settings);
+        assert!(notice.is_some());
+        let mut destinations: Vec<_> = moves.iter().map(|(_, m)| m.destination).collect();
+        destinations.sort();
+        assert_eq!(vec![46, 52], destinations);
+    }
+
+    #[test]
+    fn test_must_use_larger_die() {
+        // neither die can be used after the other, so only the 5 can be used
+        let mut board = vec![marble(1, Player::Red, 46)];
+        board.extend((2..=5).map(|id| marble(id, Player::Red, BOARD.len())));
+        let dice = Dice::new(2, 5);
+        let status = PowerUpStatus::default();
+        let settings = GameSettings::default();
+        let moves = generate_moves(Player::Red, &board, &dice, &status, &settings);
+        assert_eq!(2, moves.len());
+
+        let (moves, notice) = enforce_both_dice(moves, Player::Red, &board, &dice, &status, &settings);
+        assert!(notice.is_some());
+        assert_eq!(1, moves.len());
+        assert_eq!(51, moves[0].1.destination);
+        assert_eq!(WhichDie::Two, moves[0].1.which);
+    }
+
+    #[test]
+    fn test_blockade() {
+        // blue blockade on red's 20 and 21
+        let board = [
+            marble(1, Player::Red, 17),
+            marble(2, Player::Blue, Player::shift_index(20, Player::Red, Player::Blue)),
+            marble(3, Player::Blue, Player::shift_index(21, Player::Red, Player::Blue)),
+        ];
+        let dice = Dice::new(2, 5);
+        let mut status = PowerUpStatus::default();
+        let settings = GameSettings{ blockade: Some(2), ..default() };
+        let moves = generate_moves(Player::Red, &board, &dice, &status, &settings);
+        assert_eq!(1, moves.len());
+        assert_eq!(19, moves[0].1.destination);
+
+        // a blockade needs 3 in a row now
+        let settings = GameSettings{ blockade: Some(3), ..default() };
+        assert_eq!(3, generate_moves(Player::Red, &board, &dice, &status, &settings).len());
+
+        // jumping crosses blockades
+        let settings = GameSettings{ blockade: Some(2), ..default() };
+        status.jump_self_turns = 1;
+        assert_eq!(3, generate_moves(Player::Red, &board, &dice, &status, &settings).len());
+    }
+
+    fn capture_nearest_moves(board: &[MarbleState]) -> Vec<(Entity, MarbleMove)> {
+        let status = PowerUpStatus{ capture_nearest: true, ..default() };
+        generate_moves(Player::Red, board, &Dice::default(), &status, &GameSettings::default())
+    }
+
+    #[test]
+    fn test_capture_nearest_ties() {
+        // from 29 the center and tile 30 are both one move away
+        let board = [
+            marble(1, Player::Red, 29),
+            marble(2, Player::Green, CENTER_INDEX),
+            marble(3, Player::Blue, Player::shift_index(30, Player::Red, Player::Blue)),
+            marble(4, Player::Yellow, Player::shift_index(35, Player::Red, Player::Yellow)),
+        ];
+        let mut moves: Vec<_> = capture_nearest_moves(&board).into_iter().map(|(_, m)| (m.destination, m.distance)).collect();
+        moves.sort();
+        assert_eq!(vec![(30, 1), (CENTER_INDEX, 1)], moves);
+    }
+
+    #[test]
+    fn test_capture_nearest_past_last_center_entrance() {
+        // the center can't be reached after tile 29, so the marble on 40 is the nearest
+        let board = [
+            marble(1, Player::Red, 30),
+            marble(2, Player::Green, CENTER_INDEX),
+            marble(3, Player::Blue, Player::shift_index(40, Player::Red, Player::Blue)),
+        ];
+        let moves = capture_nearest_moves(&board);
+        assert_eq!(1, moves.len());
+        assert_eq!(40, moves[0].1.destination);
+
+        // with nothing else to capture there's no move at all
+        assert!(capture_nearest_moves(&board[..2]).is_empty());
+    }
+
+    #[test]
+    fn test_capture_nearest_from_center() {
+        // marbles in the center leave through the exit at 41
+        let board = [
+            marble(1, Player::Red, CENTER_INDEX),
+            marble(2, Player::Green, Player::shift_index(43, Player::Red, Player::Green)),
+            marble(3, Player::Blue, Player::shift_index(20, Player::Red, Player::Blue)),
+        ];
+        let moves = capture_nearest_moves(&board);
+        assert_eq!(1, moves.len());
+        assert_eq!((43, 3), (moves[0].1.destination, moves[0].1.distance));
+    }
+
+    #[test]
+    fn test_capture_nearest_path() {
+        assert_eq!(Some(vec![CENTER_INDEX]), capture_nearest_path(29, CENTER_INDEX));
+        assert_eq!(Some(vec![16, 17, CENTER_INDEX]), capture_nearest_path(15, CENTER_INDEX));
+        assert_eq!(None, capture_nearest_path(30, CENTER_INDEX));
+        assert_eq!(Some(vec![41, 42]), capture_nearest_path(CENTER_INDEX, 42));
+        assert_eq!(None, capture_nearest_path(CENTER_INDEX, 20));
+        assert_eq!(None, capture_nearest_path(20, 20));
+        assert_eq!(None, capture_nearest_path(20, 10));
+    }
 }
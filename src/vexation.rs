@@ -9,14 +9,19 @@ use crate::end_turn::*;
 use crate::choose_color::ChooseColorPlugin;
 use crate::dice_roll::DiceRollPlugin;
 use crate::human_turn::HumanTurnPlugin;
+use crate::input::{Bindings, InputAction};
 use crate::next_player::*;
+use crate::notice::NoticePlugin;
 use crate::power::PowerBar;
 use crate::power::PowerUpHighlights;
 use crate::power::PowerUpPlugin;
 use crate::power::PowerUpSpriteSheets;
+use crate::power_hud::PowerHudPlugin;
 use crate::process::ProcessMovePlugin;
 use crate::resources::*;
 use crate::shared_systems::*;
+use crate::targeting::TargetingPlugin;
+use crate::threat_overlay::ThreatOverlayPlugin;
 use crate::turn_setup::*;
 use rand::{Rng, thread_rng};
 use rand::distributions::Uniform;
@@ -34,6 +39,7 @@ impl Plugin for VexationPlugin {
             .add_systems(OnEnter(GameState::GameStart), create_game)
 
             // game play exit
+            .add_systems(OnEnter(GameState::GameEnd), show_winner)
             .add_systems(Update, game_end.run_if(in_state(GameState::GameEnd)))
             .add_systems(OnExit(GameState::GameEnd), destroy_game)
 
@@ -63,7 +69,7 @@ impl Plugin for VexationPlugin {
             // computer turn
             .add_systems(
                 OnEnter(GameState::ComputerTurn),
-                (clear_animation_events, computer_choose_move).chain()
+                (clear_animation_events, computer_choose_move, computer_use_power_up).chain()
             )
             .add_systems(Update, computer_move_buffer.run_if(in_state(GameState::ComputerTurn)))
 
@@ -72,7 +78,11 @@ impl Plugin for VexationPlugin {
             .add_plugins(ChooseColorPlugin)
             .add_plugins(DiceRollPlugin)
             .add_plugins(HumanTurnPlugin)
+            .add_plugins(NoticePlugin)
+            .add_plugins(PowerHudPlugin)
             .add_plugins(ProcessMovePlugin)
+            .add_plugins(TargetingPlugin)
+            .add_plugins(ThreatOverlayPlugin)
 
             // end turn
             .add_systems(Update, end_turn.run_if(in_state(GameState::EndTurn)))
@@ -85,6 +95,7 @@ pub fn create_game(
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut next_state: ResMut<NextState<GameState>>,
     asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
 ) {
     // insert resources
     commands.insert_resource(BufferTimer(Timer::from_seconds(1.0, TimerMode::Once)));
@@ -108,10 +119,14 @@ pub fn create_game(
         self_jump: load_sprite_sheet("power-ups/self-jump-sheet.png", TILE_BUTTON_SIZE.clone(), (3, 1), &asset_server, &mut texture_atlases),
         capture_nearest: load_sprite_sheet("power-ups/capture-nearest-sheet.png", TILE_BUTTON_SIZE.clone(), (3, 1), &asset_server, &mut texture_atlases),
         home_run: load_sprite_sheet("power-ups/home-run-sheet.png", TILE_BUTTON_SIZE.clone(), (3, 1), &asset_server, &mut texture_atlases),
+        swap: load_sprite_sheet("power-ups/swap-sheet.png", TILE_BUTTON_SIZE, (3, 1), &asset_server, &mut texture_atlases),
+        freeze: load_sprite_sheet("power-ups/freeze-sheet.png", TILE_BUTTON_SIZE, (3, 1), &asset_server, &mut texture_atlases),
+        shield: load_sprite_sheet("power-ups/shield-sheet.png", TILE_BUTTON_SIZE, (3, 1), &asset_server, &mut texture_atlases),
     });
     commands.insert_resource(PowerUpHighlights{
         evading: asset_server.load("power-ups/evade-capture-highlight.png"),
         self_jumping: asset_server.load("power-ups/self-jump-highlight.png"),
+        frozen: asset_server.load("power-ups/frozen-highlight.png"),
     });
 
     // pick the first player randomly
@@ -184,7 +199,34 @@ pub fn create_game(
         .insert(Hidable)
         .id()
     );
+    // human player undo UI button (to the left of the done button)
+    game_play_entities.board_entities.push(commands
+        .spawn(text_button_bundle(
+            "UNDO",
+            asset_server.load("Kenney Mini.ttf"),
+            Transform::from_xyz(-(UI_BUTTON_SIZE.x / 2.0 + TILE_SIZE * 1.5), (-WINDOW_SIZE / 2.0) + TILE_SIZE, Z_UI),
+            ButtonAction(ActionEvent(GameButtonAction::Undo)),
+            ButtonState::NotHovered,
+            ButtonSize(Vec2::new(TILE_SIZE * 2.0, TILE_SIZE)),
+        ))
+        .insert((Hidable, Visibility::Hidden, Disabled))
+        .id()
+    );
+    // human player hint UI button (to the right of the done button)
+    game_play_entities.board_entities.push(commands
+        .spawn(text_button_bundle(
+            "HINT",
+            asset_server.load("Kenney Mini.ttf"),
+            Transform::from_xyz(UI_BUTTON_SIZE.x / 2.0 + TILE_SIZE * 1.5, (-WINDOW_SIZE / 2.0) + TILE_SIZE, Z_UI),
+            ButtonAction(ActionEvent(GameButtonAction::Hint)),
+            ButtonState::NotHovered,
+            ButtonSize(Vec2::new(TILE_SIZE * 2.0, TILE_SIZE)),
+        ))
+        .insert((Hidable, Visibility::Hidden))
+        .id()
+    );
     commands.insert_resource(game_play_entities);
+    commands.insert_resource(UndoData::default());
 
     // highlight data
     commands.insert_resource(HighlightData{
@@ -198,7 +240,7 @@ pub fn create_game(
     let green_marble = asset_server.load("marbles/green-marble.png");
     let blue_marble = asset_server.load("marbles/blue-marble.png");
     let yellow_marble = asset_server.load("marbles/yellow-marble.png");
-    for (x, y) in &[(2.5, 3.5), (2.5, 4.5), (3.5, 3.), (3.5, 4.), (3.5, 5.)] {
+    for (x, y) in [(2.5, 3.5), (2.5, 4.5), (3.5, 3.), (3.5, 4.), (3.5, 5.)].iter().take(settings.marble_count) {
         // green marbles
         let origin = Transform::from_xyz(x * TILE_SIZE, y * TILE_SIZE, Z_MARBLE);
         let mut green = commands
@@ -304,8 +346,44 @@ pub fn create_game(
     next_state.set(GameState::ChooseColor);
 }
 
-pub fn game_end(mut next_state: ResMut<NextState<GameState>>) {
-    next_state.set(GameState::MainMenu);
+pub fn show_winner(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_player_data: Res<CurrentPlayerData>,
+    settings: Res<GameSettings>,
+    mut game_play_entities: ResMut<GamePlayEntities>,
+) {
+    let winner = current_player_data.player;
+    let text = if settings.team_play {
+        format!("{:?} & {:?} win!", winner, winner.partner())
+    } else {
+        format!("{:?} wins!", winner)
+    };
+    game_play_entities.board_entities.push(commands.spawn(Text2dBundle{
+        text: Text::from_section(
+            text.to_uppercase(),
+            TextStyle{
+                font: asset_server.load("Kenney Mini.ttf"),
+                font_size: 48.0,
+                color: Color::WHITE,
+            },
+        ),
+        transform: Transform::from_xyz(0., 0., Z_UI),
+        ..default()
+    }).id());
+}
+
+/// Waits for a click on the winner screen before going back to the main menu.
+pub fn game_end(
+    mouse_button_inputs: Res<Input<MouseButton>>,
+    bindings: Res<Bindings>,
+    mut input_actions: EventReader<InputAction>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let confirmed = input_actions.iter().any(|a| *a == InputAction::Confirm);
+    if mouse_button_inputs.just_pressed(bindings.click) || confirmed {
+        next_state.set(GameState::MainMenu);
+    }
 }
 
 pub fn destroy_game(